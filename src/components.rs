@@ -31,6 +31,125 @@ pub struct Viewshed {
 #[derive(Component, Debug)]
 pub struct Monster {}
 
+/// Which side a creature is on; see `faction::reaction` for who fights whom.
+#[derive(Component, Debug)]
+pub struct Faction {
+    pub name: String,
+}
+
+/// What a monster is currently up to.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AiState {
+    Sleeping,
+    Idle,
+    Wandering,
+    Hunting,
+    Fleeing,
+    Searching,
+}
+
+/// A monster's current state, plus who it is after, where it last saw them and
+/// how much longer it will keep looking for them.
+#[derive(Component, Debug)]
+pub struct Brain {
+    pub state: AiState,
+    pub target: Option<Entity>,
+    pub last_seen: Option<rltk::Point>,
+    pub patience: i32,
+}
+
+/// What the player has told their allies to do.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum AllyCommand {
+    Follow,
+    Stay,
+    Attack(Entity),
+}
+
+/// A creature fighting on the player's side. It keeps close to the player unless told otherwise.
+#[derive(Component, Debug)]
+pub struct Ally {
+    pub command: AllyCommand,
+}
+
+/// A creature that will join the player for this much gold.
+#[derive(Component, Debug)]
+pub struct Hireable {
+    pub fee: i32,
+}
+
+/// Item property: turns whatever it is used on into an ally.
+#[derive(Component, Debug)]
+pub struct Charms {}
+
+/// A monster that picks up items it comes across, and knows how to use them.
+#[derive(Component, Debug)]
+pub struct Scavenger {}
+
+/// Rolled this many times on the named table in `spawner` when its owner dies.
+#[derive(Component, Debug)]
+pub struct LootTable {
+    pub table: String,
+    pub rolls: i32,
+}
+
+/// The remains of something that died, which rot away after this many turns.
+#[derive(Component, Debug)]
+pub struct Corpse {
+    pub decay: i32,
+}
+
+/// A purely visual effect, removed once this many milliseconds have passed.
+#[derive(Component, Debug)]
+pub struct ParticleLifetime {
+    pub lifetime_ms: f32,
+}
+
+/// Something that lights up the tiles around it, fading out towards the edge of its range.
+#[derive(Component, Debug)]
+pub struct LightSource {
+    pub colour: RGB,
+    pub range: i32,
+}
+
+/// A one-of-a-kind named monster, recorded in the `UniqueRegistry` when it spawns.
+#[derive(Component, Debug)]
+pub struct Unique {}
+
+/// A monster that hears noises this many tiles further away than their volume.
+#[derive(Component, Debug)]
+pub struct Hearing {
+    pub acuity: i32,
+}
+
+/// How quietly something goes about its business. Each point of skill muffles every
+/// noise it makes by a tile, and sneaking softens footsteps further at the cost of
+/// spending a turn creeping after every step.
+#[derive(Component, Debug)]
+pub struct Stealth {
+    pub skill: i32,
+    pub sneaking: bool,
+    pub creeping: bool,
+}
+
+/// A sound made this turn, which carries this many tiles around walls.
+#[derive(Component, Debug)]
+pub struct MakesNoise {
+    pub volume: i32,
+}
+
+/// A monster that attacks from range with the spells in its backpack, and
+/// tries to stay this far from its target.
+#[derive(Component, Debug)]
+pub struct Caster {
+    pub preferred_distance: i32,
+}
+
+/// An innate ability rather than a carried item: used through the same
+/// pipeline as items, but never used up or dropped.
+#[derive(Component, Debug)]
+pub struct Spell {}
+
 #[derive(Component, Debug)]
 pub struct Name {
     pub name: String,
@@ -55,6 +174,27 @@ pub struct WantsToMelee {
 #[derive(Component, Debug)]
 pub struct SufferDamage {
     pub amount: i32,
+    pub source: Entity,
+}
+
+impl SufferDamage {
+    /// Queues `amount` damage against `victim`, on top of anything already dealt to
+    /// them this turn. The latest attacker gets the credit.
+    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32, source: Entity) {
+        if let Some(suffering) = store.get_mut(victim) {
+            suffering.amount += amount;
+            suffering.source = source;
+        } else {
+            store.insert(victim, SufferDamage{ amount, source }).expect("Unable to insert damage");
+        }
+    }
+}
+
+/// Whoever hurt this entity most recently, so its death can be put down to them.
+#[derive(Component, Debug)]
+pub struct LastHitBy {
+    pub source: Entity,
+    pub name: String,
 }
 
 #[derive(Component, Debug)]
@@ -105,7 +245,204 @@ pub struct AreaOfEffect {
     pub radius : i32
 }
 
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum StatusKind {
+    Confusion,
+    Poison,
+    Regeneration,
+    Haste,
+    Slow,
+    Blindness,
+    Paralysis,
+    DetectMonsters,
+    DetectItems,
+}
+
+impl StatusKind {
+    /// Adjective used by the HUD and the log, e.g. "Poisoned".
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusKind::Confusion => "Confused",
+            StatusKind::Poison => "Poisoned",
+            StatusKind::Regeneration => "Regenerating",
+            StatusKind::Haste => "Hasted",
+            StatusKind::Slow => "Slowed",
+            StatusKind::Blindness => "Blind",
+            StatusKind::Paralysis => "Paralysed",
+            StatusKind::DetectMonsters => "Sensing Monsters",
+            StatusKind::DetectItems => "Sensing Items",
+        }
+    }
+
+    /// Poison builds up with every dose; everything else just refreshes its duration.
+    pub fn intensifies(self) -> bool {
+        self == StatusKind::Poison
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub turns: i32,
+    pub potency: i32,
+    /// Who inflicted it, if anyone, so harm it does over time is put down to them.
+    pub source: Option<Entity>,
+}
+
+/// Timed statuses currently affecting an entity.
+#[derive(Component, Debug, Default)]
+pub struct StatusEffects {
+    pub effects: Vec<StatusEffect>,
+}
+
+impl StatusEffects {
+    pub fn get(&self, kind: StatusKind) -> Option<&StatusEffect> {
+        self.effects.iter().find(|e| e.kind == kind)
+    }
+
+    pub fn has(&self, kind: StatusKind) -> bool {
+        self.get(kind).is_some()
+    }
+
+    /// How many actions the owner gets on its turn: paralysis and every other
+    /// slowed turn give none, haste gives two.
+    pub fn actions(&self) -> i32 {
+        if self.has(StatusKind::Paralysis) {
+            return 0;
+        }
+        if let Some(slow) = self.get(StatusKind::Slow) {
+            if slow.turns % 2 == 1 {
+                return 0;
+            }
+        }
+        if self.has(StatusKind::Haste) {
+            return 2;
+        }
+        1
+    }
+}
+
+/// Item property: applies a status to whatever it is used on.
+#[derive(Component, Debug)]
+pub struct InflictsStatus {
+    pub kind: StatusKind,
+    pub turns: i32,
+    pub potency: i32,
+}
+
+/// Item property: reveals the true names of everything the user is carrying.
+#[derive(Component, Debug)]
+pub struct IdentifiesItems {}
+
+/// Item property: a reusable item (like a wand) that runs out after a number of uses.
+#[derive(Component, Debug)]
+pub struct Charges {
+    pub remaining: i32,
+}
+
+/// How heavy an item is to carry around.
+#[derive(Component, Debug)]
+pub struct Weight {
+    pub weight: i32,
+}
+
+/// Carrying more than this entity's capacity; it fights at a penalty until it drops something.
+#[derive(Component, Debug)]
+pub struct Burdened {}
+
+/// How much gold an entity is carrying.
+#[derive(Component, Debug)]
+pub struct Wallet {
+    pub gold: i32,
+}
+
+/// A pile of gold on the floor, collected automatically by anyone with a `Wallet`.
+#[derive(Component, Debug)]
+pub struct Gold {
+    pub amount: i32,
+}
+
+/// What an item sells for in a shop.
+#[derive(Component, Debug)]
+pub struct Value {
+    pub gold: i32,
+}
+
+/// An NPC that trades: its stock is whatever is in its backpack.
+#[derive(Component, Debug)]
+pub struct Vendor {}
+
+/// Something that holds items, like a chest. Its contents are tagged with `InContainer`.
+#[derive(Component, Debug)]
+pub struct Container {}
+
+#[derive(Component, Debug, Clone)]
+pub struct InContainer {
+    pub container: Entity,
+}
+
+/// A container that has to be forced open first.
+#[derive(Component, Debug)]
+pub struct Locked {}
+
+/// A container that springs its `InflictsStatus` on whoever opens it, once.
+#[derive(Component, Debug)]
+pub struct Trapped {}
+
+/// Item property: shatters when thrown, splashing its effect around where it lands.
+#[derive(Component, Debug)]
+pub struct Fragile {}
+
+#[derive(Component, Debug)]
+pub struct WantsToThrowItem {
+    pub item: Entity,
+    pub target: rltk::Point,
+}
+
+/// Item property: reveals the layout of the whole level.
 #[derive(Component, Debug)]
-pub struct Confusion {
-    pub turns: i32
+pub struct MagicMapper {}
+
+/// Item property: moves the user to the targeted tile or, without a target, somewhere random.
+#[derive(Component, Debug)]
+pub struct Teleports {}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum BucStatus {
+    Blessed,
+    Uncursed,
+    Cursed,
 }
+
+impl BucStatus {
+    /// Blessed items work half again as well; cursed ones only half as well.
+    pub fn scale(self, amount: i32) -> i32 {
+        match self {
+            BucStatus::Blessed => amount * 3 / 2,
+            BucStatus::Uncursed => amount,
+            BucStatus::Cursed => amount / 2,
+        }
+    }
+
+    /// Cursed items turn some statuses into their opposite.
+    pub fn status_kind(self, kind: StatusKind) -> StatusKind {
+        match (self, kind) {
+            (BucStatus::Cursed, StatusKind::Haste) => StatusKind::Slow,
+            (BucStatus::Cursed, StatusKind::Regeneration) => StatusKind::Poison,
+            (BucStatus::Cursed, StatusKind::DetectMonsters) => StatusKind::Blindness,
+            (BucStatus::Cursed, StatusKind::DetectItems) => StatusKind::Blindness,
+            _ => kind,
+        }
+    }
+}
+
+/// Whether an item is blessed, uncursed or cursed, and whether the player knows which.
+#[derive(Component, Debug)]
+pub struct Buc {
+    pub status: BucStatus,
+    pub known: bool,
+}
+
+/// Item property: lifts curses from everything the user carries, and reveals their state.
+#[derive(Component, Debug)]
+pub struct RemovesCurse {}
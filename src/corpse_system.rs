@@ -0,0 +1,28 @@
+extern crate specs;
+use super::{Corpse, RunState};
+use specs::prelude::*;
+
+pub struct CorpseDecaySystem {}
+
+impl<'a> System<'a> for CorpseDecaySystem {
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        Entities<'a>,
+        WriteStorage<'a, Corpse>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (runstate, entities, mut corpses) = data;
+
+        // Corpses rot once a turn, and are gone once there's nothing left
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+        for (entity, corpse) in (&entities, &mut corpses).join() {
+            corpse.decay -= 1;
+            if corpse.decay < 1 {
+                entities.delete(entity).expect("Unable to delete corpse");
+            }
+        }
+    }
+}
@@ -1,22 +1,52 @@
 extern crate specs;
 use specs::prelude::*;
 extern crate rltk;
-use super::{gamelog::GameLog, CombatStats, Name, Player, SufferDamage};
-use rltk::console;
+use super::{
+    gamelog::{GameLog, GameStats},
+    spawner, CombatStats, InBackpack, LastHitBy, LootTable, Map, Monster, Name, Player, Position,
+    Renderable, RunState, Spell, StatusEffects, StatusKind, SufferDamage, Unique,
+};
+use rltk::RGB;
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
+        WriteExpect<'a, Map>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Position>,
+        Entities<'a>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, LastHitBy>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (mut map, mut stats, mut damage, positions, entities, names, mut last_hit) = data;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
+        for (entity, stats, damage, pos) in
+            (&entities, &mut stats, &damage, (&positions).maybe()).join()
+        {
             stats.hp -= damage.amount;
+            let name = names
+                .get(damage.source)
+                .map_or("something".to_string(), |n| n.name.clone());
+            last_hit
+                .insert(
+                    entity,
+                    LastHitBy {
+                        source: damage.source,
+                        name,
+                    },
+                )
+                .expect("Unable to insert last hit");
+            // Anyone who gets hurt leaves a bloodstain where they stand
+            if let Some(pos) = pos {
+                if damage.amount > 0 {
+                    let idx = map.xy_idx(pos.x, pos.y);
+                    map.bloodstains.insert(idx);
+                }
+            }
         }
 
         damage.clear();
@@ -25,12 +55,24 @@ impl<'a> System<'a> for DamageSystem {
 
 pub fn delete_the_dead(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
+    let mut loot: Vec<(String, i32, i32, i32)> = Vec::new();
+    let mut corpses: Vec<(String, RGB, i32, i32)> = Vec::new();
     // Using a scope to make the borrow checker happy
     {
         let mut log = ecs.write_resource::<GameLog>();
         let names = ecs.read_storage::<Name>();
         let combat_stats = ecs.read_storage::<CombatStats>();
         let players = ecs.read_storage::<Player>();
+        let uniques = ecs.read_storage::<Unique>();
+        let loot_tables = ecs.read_storage::<LootTable>();
+        let positions = ecs.read_storage::<Position>();
+        let monsters = ecs.read_storage::<Monster>();
+        let renderables = ecs.read_storage::<Renderable>();
+        let last_hit = ecs.read_storage::<LastHitBy>();
+        let statuses = ecs.read_storage::<StatusEffects>();
+        let mut game_stats = ecs.write_resource::<GameStats>();
+        let mut runstate = ecs.write_resource::<RunState>();
+        let player_entity = *ecs.fetch::<Entity>();
         let entities = ecs.entities();
 
         for (entity, stats) in (&entities, &combat_stats).join() {
@@ -38,20 +80,102 @@ pub fn delete_the_dead(ecs: &mut World) {
                 let player = players.get(entity);
                 match player {
                     None => {
+                        if last_hit
+                            .get(entity)
+                            .is_some_and(|h| h.source == player_entity)
+                        {
+                            game_stats.kills += 1;
+                        }
                         let victim_name = names.get(entity);
                         if let Some(victim_name) = victim_name {
-                            log.entries
-                                .insert(0, format!("{} is dead", &victim_name.name));
+                            if uniques.get(entity).is_some() {
+                                log.entries
+                                    .insert(0, format!("{} has been slain!", &victim_name.name));
+                            } else {
+                                log.entries
+                                    .insert(0, format!("{} is dead", &victim_name.name));
+                            }
+                        }
+                        if let (Some(table), Some(pos)) =
+                            (loot_tables.get(entity), positions.get(entity))
+                        {
+                            loot.push((table.table.clone(), table.rolls, pos.x, pos.y));
+                        }
+                        if let (Some(_monster), Some(name), Some(render), Some(pos)) = (
+                            monsters.get(entity),
+                            names.get(entity),
+                            renderables.get(entity),
+                            positions.get(entity),
+                        ) {
+                            corpses.push((name.name.clone(), render.fg, pos.x, pos.y));
                         }
                         dead.push(entity)
                     }
-                    Some(_) => console::log("You are dead."),
+                    Some(_) => {
+                        // The player's body stays put, for the game over screen to be drawn over
+                        if game_stats.cause_of_death.is_none() {
+                            let poisoned = statuses
+                                .get(entity)
+                                .is_some_and(|s| s.has(StatusKind::Poison));
+                            let cause = match last_hit.get(entity) {
+                                Some(hit) if hit.source == entity => {
+                                    "Killed by your own hand".to_string()
+                                }
+                                Some(hit) => format!("Killed by {}", hit.name),
+                                None if poisoned => "Succumbed to poison".to_string(),
+                                None => "Died of unknown causes".to_string(),
+                            };
+                            game_stats.cause_of_death = Some(cause);
+                            log.entries.insert(0, "You are dead.".to_string());
+                            *runstate = RunState::GameOver;
+                        }
+                    }
                 }
             }
         }
     }
 
+    // Spells die with whoever knew them, and anything else they carried falls to the floor
+    {
+        let entities = ecs.entities();
+        let spells = ecs.read_storage::<Spell>();
+        let mut backpack = ecs.write_storage::<InBackpack>();
+        let mut positions = ecs.write_storage::<Position>();
+        let carried: Vec<(Entity, Entity)> = (&entities, &backpack)
+            .join()
+            .filter(|(_e, pack)| dead.contains(&pack.owner))
+            .map(|(e, pack)| (e, pack.owner))
+            .collect();
+        for (item, owner) in carried {
+            if spells.get(item).is_some() {
+                dead.push(item);
+                continue;
+            }
+            let drop_at = positions
+                .get(owner)
+                .map(|pos| Position { x: pos.x, y: pos.y });
+            if let Some(drop_at) = drop_at {
+                backpack.remove(item);
+                positions
+                    .insert(item, drop_at)
+                    .expect("Unable to drop item");
+            }
+        }
+    }
+
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
+
+    // Leave the bodies where they fell
+    for (name, fg, x, y) in corpses {
+        spawner::corpse(ecs, x, y, &name, fg);
+    }
+
+    // Roll each victim's loot table, leaving whatever turns up where they fell
+    for (table, rolls, x, y) in loot {
+        for _ in 0..rolls {
+            spawner::drop_loot(ecs, &table, x, y);
+        }
+    }
 }
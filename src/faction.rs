@@ -0,0 +1,38 @@
+/// How members of one faction behave towards members of another.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Reaction {
+    Attack,
+    Ignore,
+    Flee,
+}
+
+pub const PLAYER: &str = "Player";
+pub const ORCS: &str = "Orcs";
+pub const GOBLINS: &str = "Goblins";
+pub const WILDLIFE: &str = "Wildlife";
+pub const MERCENARIES: &str = "Mercenaries";
+
+/// Who does what to whom. Anything not listed here is ignored, and factions
+/// never turn on their own members.
+const REACTIONS: &[(&str, &str, Reaction)] = &[
+    (PLAYER, ORCS, Reaction::Attack),
+    (PLAYER, GOBLINS, Reaction::Attack),
+    (ORCS, PLAYER, Reaction::Attack),
+    (ORCS, GOBLINS, Reaction::Attack),
+    (GOBLINS, PLAYER, Reaction::Attack),
+    (GOBLINS, ORCS, Reaction::Attack),
+    (GOBLINS, WILDLIFE, Reaction::Attack),
+    (WILDLIFE, PLAYER, Reaction::Flee),
+    (WILDLIFE, GOBLINS, Reaction::Flee),
+];
+
+/// How a member of `mine` reacts on spotting a member of `theirs`.
+pub fn reaction(mine: &str, theirs: &str) -> Reaction {
+    if mine == theirs {
+        return Reaction::Ignore;
+    }
+    REACTIONS
+        .iter()
+        .find(|(from, to, _)| *from == mine && *to == theirs)
+        .map_or(Reaction::Ignore, |r| r.2)
+}
@@ -0,0 +1,203 @@
+extern crate specs;
+use super::{
+    faction::{reaction, Reaction},
+    AiState, Brain, CombatStats, Faction, Item, Map, Position, RunState, TileType,
+};
+use rltk::Point;
+use specs::prelude::*;
+use std::collections::{HashMap, VecDeque};
+
+/// Scaling applied to an approach map to turn it into a flee map. Going past -1
+/// makes fleeing monsters prefer routes that lead somewhere over dead-end corners.
+const FLEE_FACTOR: f32 = -1.2;
+
+/// Distance fields shared by every monster, rebuilt once per monster turn. Each map
+/// holds a cost per tile; walking downhill leads towards (or, for flee maps, away from)
+/// the goals it was built from.
+#[derive(Default)]
+pub struct FlowMaps {
+    /// Per faction: leads to the nearest creature that faction wants to attack.
+    pub approach: HashMap<String, Vec<f32>>,
+    /// Per faction: leads away from everything that faction fights or fears.
+    pub flee: HashMap<String, Vec<f32>>,
+    /// Per faction: leads to the nearest place one of its members last saw an enemy.
+    pub search: HashMap<String, Vec<f32>>,
+    /// Leads to the player, for allies keeping up with them.
+    pub player: Vec<f32>,
+    /// Leads to the nearest item lying on the floor. Empty if there are none.
+    pub items: Vec<f32>,
+}
+
+impl FlowMaps {
+    /// The neighbouring open tile that goes furthest downhill on `field`, if any is
+    /// lower than where we are now.
+    pub fn downhill(map: &Map, field: &[f32], x: i32, y: i32) -> Option<(i32, i32)> {
+        let mut best = (field[map.xy_idx(x, y)], None);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 1 || nx > map.width - 2 || ny < 1 || ny > map.height - 2 {
+                    continue;
+                }
+                let idx = map.xy_idx(nx, ny);
+                if map.blocked[idx] || field[idx] >= best.0 {
+                    continue;
+                }
+                best = (field[idx], Some((nx, ny)));
+            }
+        }
+        best.1
+    }
+}
+
+pub struct FlowMapSystem {}
+
+impl<'a> System<'a> for FlowMapSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, FlowMaps>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, CombatStats>,
+        ReadStorage<'a, Item>,
+        ReadStorage<'a, Brain>,
+        ReadExpect<'a, Point>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            runstate,
+            map,
+            mut flow_maps,
+            positions,
+            factions,
+            combat_stats,
+            items,
+            brains,
+            player_pos,
+        ) = data;
+
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        let creatures: Vec<(usize, &str)> = (&positions, &factions, &combat_stats)
+            .join()
+            .map(|(pos, faction, _stats)| (map.xy_idx(pos.x, pos.y), faction.name.as_str()))
+            .collect();
+        let mut faction_names: Vec<&str> = creatures.iter().map(|c| c.1).collect();
+        faction_names.sort_unstable();
+        faction_names.dedup();
+
+        let loot: Vec<(usize, f32)> = (&positions, &items)
+            .join()
+            .map(|(pos, _item)| (map.xy_idx(pos.x, pos.y), 0.0))
+            .collect();
+        flow_maps.items = if loot.is_empty() {
+            Vec::new()
+        } else {
+            flow_field(&map, &loot)
+        };
+
+        flow_maps.player = flow_field(&map, &[(map.xy_idx(player_pos.x, player_pos.y), 0.0)]);
+
+        flow_maps.approach.clear();
+        flow_maps.flee.clear();
+        flow_maps.search.clear();
+        for mine in faction_names {
+            let leads: Vec<(usize, f32)> = (&factions, &brains)
+                .join()
+                .filter(|(faction, brain)| {
+                    faction.name == mine
+                        && (brain.state == AiState::Hunting || brain.state == AiState::Searching)
+                })
+                .filter_map(|(_faction, brain)| brain.last_seen)
+                .map(|p| (map.xy_idx(p.x, p.y), 0.0))
+                .collect();
+            if !leads.is_empty() {
+                flow_maps
+                    .search
+                    .insert(mine.to_string(), flow_field(&map, &leads));
+            }
+
+            let mut prey = Vec::new();
+            let mut danger = Vec::new();
+            for (idx, theirs) in creatures.iter() {
+                match reaction(mine, theirs) {
+                    Reaction::Attack => {
+                        prey.push((*idx, 0.0));
+                        danger.push((*idx, 0.0));
+                    }
+                    Reaction::Flee => danger.push((*idx, 0.0)),
+                    Reaction::Ignore => {}
+                }
+            }
+
+            if !prey.is_empty() {
+                flow_maps
+                    .approach
+                    .insert(mine.to_string(), flow_field(&map, &prey));
+            }
+            if !danger.is_empty() {
+                // Invert the approach field and let it settle again, so the lowest
+                // ground is the safest place within reach rather than the nearest corner
+                let towards = flow_field(&map, &danger);
+                let seeds: Vec<(usize, f32)> = towards
+                    .iter()
+                    .enumerate()
+                    .filter(|(_idx, cost)| **cost < f32::MAX)
+                    .map(|(idx, cost)| (idx, cost * FLEE_FACTOR))
+                    .collect();
+                flow_maps
+                    .flee
+                    .insert(mine.to_string(), flow_field(&map, &seeds));
+            }
+        }
+    }
+}
+
+/// Builds a distance field out from the given seed tiles and their starting costs,
+/// walking round walls but straight through creatures, who will have moved by the
+/// time anyone gets there.
+pub fn flow_field(map: &Map, seeds: &[(usize, f32)]) -> Vec<f32> {
+    flow_field_within(map, seeds, f32::MAX)
+}
+
+/// Like `flow_field`, but stops spreading once the cost passes `max_cost`. Anything
+/// further away is left at `f32::MAX`.
+pub fn flow_field_within(map: &Map, seeds: &[(usize, f32)], max_cost: f32) -> Vec<f32> {
+    let mut field = vec![f32::MAX; map.tiles.len()];
+    let mut open: VecDeque<usize> = VecDeque::new();
+    for (idx, cost) in seeds.iter() {
+        if *cost < field[*idx] {
+            field[*idx] = *cost;
+            open.push_back(*idx);
+        }
+    }
+
+    while let Some(idx) = open.pop_front() {
+        let x = idx as i32 % map.width;
+        let y = idx as i32 / map.width;
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let (nx, ny) = (x + dx, y + dy);
+                if (dx == 0 && dy == 0) || nx < 0 || nx >= map.width || ny < 0 || ny >= map.height {
+                    continue;
+                }
+                let next = map.xy_idx(nx, ny);
+                if map.tiles[next] == TileType::Wall {
+                    continue;
+                }
+                let step = if dx != 0 && dy != 0 { 1.45 } else { 1.0 };
+                if field[idx] + step <= max_cost && field[idx] + step < field[next] {
+                    field[next] = field[idx] + step;
+                    open.push_back(next);
+                }
+            }
+        }
+    }
+
+    field
+}
@@ -1,3 +1,11 @@
 pub struct GameLog {
     pub entries: Vec<String>,
 }
+
+/// Running totals for the current game, shown on the game over screen.
+#[derive(Default)]
+pub struct GameStats {
+    pub turns: i32,
+    pub kills: i32,
+    pub cause_of_death: Option<String>,
+}
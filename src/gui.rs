@@ -1,8 +1,10 @@
 extern crate rltk;
 use rltk::{Console, Point, Rltk, VirtualKeyCode, RGB};
 extern crate specs;
-use super::{gamelog::GameLog, CombatStats, InBackpack, Map, Name, Player, Position, State,
-            Viewshed };
+use super::{gamelog::{GameLog, GameStats}, identification::Identification, Buc, Charges, CombatStats, InBackpack,
+            InContainer, Map, Name, Player, Position, State, Stealth, StatusEffects, StatusKind, Value,
+            Viewshed, Wallet };
+use super::inventory_system::{buc_name, carried_weight, carry_capacity, max_carry_weight};
 use specs::prelude::*;
 
 #[derive(PartialEq, Copy, Clone)]
@@ -12,6 +14,20 @@ pub enum ItemMenuResult {
     Selected,
 }
 
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorMode {
+    Buy,
+    Sell,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum VendorResult {
+    Cancel,
+    NoResponse,
+    Switch,
+    Selected,
+}
+
 pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     ctx.draw_box(
         0,
@@ -45,14 +61,23 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         );
     }
 
+    let wallets = ecs.read_storage::<Wallet>();
+    for (_player, wallet) in (&players, &wallets).join() {
+        ctx.print_color(
+            2,
+            43,
+            RGB::named(rltk::GOLD),
+            RGB::named(rltk::BLACK),
+            &format!(" {} gp ", wallet.gold),
+        );
+    }
+
+    draw_status_bar(ecs, ctx);
+
     let log = ecs.fetch::<GameLog>();
 
-    let mut y = 44;
-    for txt in log.entries.iter() {
-        if y < 49 {
-            ctx.print(2, y, &txt.to_string());
-        }
-        y += 1;
+    for (y, txt) in (44..49).zip(log.entries.iter()) {
+        ctx.print(2, y, &txt.to_string());
     }
 
     // Draw mouse cursor
@@ -62,20 +87,66 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     draw_tooltips(ecs, ctx);
 }
 
+fn status_color(kind: StatusKind) -> RGB {
+    match kind {
+        StatusKind::Confusion => RGB::named(rltk::MAGENTA),
+        StatusKind::Poison => RGB::named(rltk::GREEN),
+        StatusKind::Regeneration => RGB::named(rltk::PINK),
+        StatusKind::Haste => RGB::named(rltk::YELLOW),
+        StatusKind::Slow => RGB::named(rltk::BLUE),
+        StatusKind::Blindness => RGB::named(rltk::GREY),
+        StatusKind::Paralysis => RGB::named(rltk::CYAN),
+        StatusKind::DetectMonsters => RGB::named(rltk::RED),
+        StatusKind::DetectItems => RGB::named(rltk::GOLD),
+    }
+}
+
+/// Lists the player's active statuses along the bottom edge of the log box.
+fn draw_status_bar(ecs: &World, ctx: &mut Rltk) {
+    let player_entity = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let stealth = ecs.read_storage::<Stealth>();
+
+    let mut x = 2;
+    if let Some(status) = statuses.get(*player_entity) {
+        for effect in status.effects.iter() {
+            let label = format!(" {} ({}) ", effect.kind.name(), effect.turns);
+            ctx.print_color(
+                x,
+                49,
+                status_color(effect.kind),
+                RGB::named(rltk::BLACK),
+                &label,
+            );
+            x += label.len() as i32;
+        }
+    }
+    if stealth.get(*player_entity).is_some_and(|s| s.sneaking) {
+        ctx.print_color(x, 49, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), " Sneaking ");
+    }
+}
+
 fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let map = ecs.fetch::<Map>();
     let names = ecs.read_storage::<Name>();
     let positions = ecs.read_storage::<Position>();
+    let identification = ecs.fetch::<Identification>();
 
     let mouse_pos = ctx.mouse_pos();
     if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height {
         return;
     }
     let mut tooltip: Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 {
-            tooltip.push(name.name.to_string());
+    let idx = map.xy_idx(mouse_pos.0, mouse_pos.1);
+    if map.visible_tiles[idx] {
+        for (name, position) in (&names, &positions).join() {
+            if position.x == mouse_pos.0 && position.y == mouse_pos.1 {
+                tooltip.push(identification.display_name(&name.name));
+            }
         }
+    } else if let Some(thing) = map.remembered.get(&idx) {
+        // Out of sight, all the player has to go on is what they saw last
+        tooltip.push(format!("{} (remembered)", identification.display_name(&thing.name)));
     }
 
     if !tooltip.is_empty() {
@@ -90,8 +161,7 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
         if mouse_pos.0 > 40 {
             let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
             let left_x = mouse_pos.0 - width;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
                 ctx.print_color(
                     left_x,
                     y,
@@ -106,23 +176,21 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::GREY),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::GREY),
-                &"->".to_string(),
+                "->",
             );
         } else {
             let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
             let left_x = mouse_pos.0 + 3;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (y, s) in (mouse_pos.1..).zip(tooltip.iter()) {
                 ctx.print_color(
                     left_x + 1,
                     y,
@@ -137,63 +205,123 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::GREY),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::GREY),
-                &"<-".to_string(),
+                "<-",
             );
         }
     }
 }
 
-pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let entities = gs.ecs.entities();
+/// Groups an owner's backpack into menu lines: identical items share a line
+/// with a count, and items with charges show how many they have left.
+pub fn backpack_stacks(ecs: &World, owner: Entity) -> Vec<(String, Entity)> {
+    let items: Vec<Entity> = {
+        let backpack = ecs.read_storage::<InBackpack>();
+        let entities = ecs.entities();
+        (&entities, &backpack)
+            .join()
+            .filter(|item| item.1.owner == owner)
+            .map(|item| item.0)
+            .collect()
+    };
+    item_stacks(ecs, &items)
+}
+
+/// The same grouping as `backpack_stacks`, for whatever is inside a container.
+pub fn container_stacks(ecs: &World, container: Entity) -> Vec<(String, Entity)> {
+    let items: Vec<Entity> = {
+        let in_container = ecs.read_storage::<InContainer>();
+        let entities = ecs.entities();
+        (&entities, &in_container)
+            .join()
+            .filter(|item| item.1.container == container)
+            .map(|item| item.0)
+            .collect()
+    };
+    item_stacks(ecs, &items)
+}
+
+fn item_stacks(ecs: &World, items: &[Entity]) -> Vec<(String, Entity)> {
+    let names = ecs.read_storage::<Name>();
+    let charges = ecs.read_storage::<Charges>();
+    let bucs = ecs.read_storage::<Buc>();
+    let identification = ecs.fetch::<Identification>();
+
+    let mut stacks: Vec<(String, i32, Entity)> = Vec::new();
+    for entity in items.iter() {
+        let name = match names.get(*entity) {
+            Some(name) => name,
+            None => continue,
+        };
+        let mut display_name = identification.display_name(&name.name);
+        if let Some(buc) = bucs.get(*entity) {
+            if buc.known {
+                display_name = format!("{} {}", buc_name(buc.status), display_name);
+            }
+        }
+        if let Some(charges) = charges.get(*entity) {
+            stacks.push((format!("{} ({})", display_name, charges.remaining), 1, *entity));
+            continue;
+        }
+        match stacks.iter_mut().find(|s| s.0 == display_name) {
+            Some(stack) => stack.1 += 1,
+            None => stacks.push((display_name, 1, *entity)),
+        }
+    }
+
+    stacks
+        .into_iter()
+        .map(|(name, count, entity)| {
+            if count > 1 {
+                (format!("{} x{}", name, count), entity)
+            } else {
+                (name, entity)
+            }
+        })
+        .collect()
+}
 
-    let inventory = (&backpack, &names)
-        .join()
-        .filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+/// Draws a lettered list of items in a box and returns whichever one the player picks.
+fn item_list_menu(
+    ctx: &mut Rltk,
+    title: &str,
+    items: &[(String, Entity)],
+) -> (ItemMenuResult, Option<Entity>) {
+    let count = items.len();
 
-    let mut y = (25 - (count / 2)) as i32;
+    let top = (25 - (count / 2)) as i32;
     ctx.draw_box(
         15,
-        y - 2,
-        31,
+        top - 2,
+        40,
         (count + 3) as i32,
         RGB::named(rltk::WHITE),
         RGB::named(rltk::BLACK),
     );
     ctx.print_color(
         18,
-        y - 2,
+        top - 2,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
-        "Inventory",
+        title,
     );
     ctx.print_color(
         18,
-        y + count as i32 + 1,
+        top + count as i32 + 1,
         RGB::named(rltk::YELLOW),
         RGB::named(rltk::BLACK),
         "ESCAPE to cancel",
     );
 
-    let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names)
-        .join()
-        .filter(|item| item.1.owner == *player_entity)
-    {
+    for (j, (y, (name, _entity))) in (top..).zip(items.iter()).enumerate() {
         ctx.set(
             17,
             y,
@@ -216,10 +344,7 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             rltk::to_cp437(')'),
         );
 
-        ctx.print(21, y, &name.name.to_string());
-        equippable.push(entity);
-        y += 1;
-        j += 1;
+        ctx.print(21, y, name);
     }
 
     match ctx.key {
@@ -229,10 +354,7 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
             _ => {
                 let selection = rltk::letter_to_option(key);
                 if selection > -1 && selection < count as i32 {
-                    return (
-                        ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
-                    );
+                    return (ItemMenuResult::Selected, Some(items[selection as usize].1));
                 }
                 (ItemMenuResult::NoResponse, None)
             }
@@ -240,89 +362,170 @@ pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option
     }
 }
 
-pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
-    let player_entity = gs.ecs.fetch::<Entity>();
-    let names = gs.ecs.read_storage::<Name>();
-    let backpack = gs.ecs.read_storage::<InBackpack>();
-    let entities = gs.ecs.entities();
-
-    let inventory = (&backpack, &names)
-        .join()
-        .filter(|item| item.0.owner == *player_entity);
-    let count = inventory.count();
+pub fn show_inventory(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let items = backpack_stacks(&gs.ecs, player_entity);
+    let result = item_list_menu(ctx, "Inventory", &items);
+    draw_pack_weight(&gs.ecs, ctx, player_entity, (25 - (items.len() / 2)) as i32 - 2);
+    result
+}
 
-    let mut y = (25 - (count / 2)) as i32;
-    ctx.draw_box(
-        15,
-        y - 2,
-        31,
-        (count + 3) as i32,
-        RGB::named(rltk::WHITE),
-        RGB::named(rltk::BLACK),
-    );
-    ctx.print_color(
-        18,
-        y - 2,
-        RGB::named(rltk::YELLOW),
-        RGB::named(rltk::BLACK),
-        "Drop Which Item?",
-    );
-    ctx.print_color(
-        18,
-        y + count as i32 + 1,
-        RGB::named(rltk::YELLOW),
-        RGB::named(rltk::BLACK),
-        "ESCAPE to cancel",
-    );
+/// Shows carried weight against the hard limit on the inventory's title bar,
+/// in red once the pack is heavy enough to burden its owner.
+fn draw_pack_weight(ecs: &World, ctx: &mut Rltk, owner: Entity, y: i32) {
+    let combat_stats = ecs.read_storage::<CombatStats>();
 
-    let mut equippable: Vec<Entity> = Vec::new();
-    let mut j = 0;
-    for (entity, _pack, name) in (&entities, &backpack, &names)
-        .join()
-        .filter(|item| item.1.owner == *player_entity)
-    {
-        ctx.set(
-            17,
+    if let Some(stats) = combat_stats.get(owner) {
+        let carried = carried_weight(ecs, owner);
+        let color = if carried > carry_capacity(stats) {
+            RGB::named(rltk::RED)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color(
+            38,
             y,
-            RGB::named(rltk::WHITE),
+            color,
             RGB::named(rltk::BLACK),
-            rltk::to_cp437('('),
+            &format!("Weight: {}/{}", carried, max_carry_weight(stats)),
         );
-        ctx.set(
+    }
+}
+
+pub fn drop_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let items = backpack_stacks(&gs.ecs, player_entity);
+    item_list_menu(ctx, "Drop Which Item?", &items)
+}
+
+pub fn throw_item_menu(gs: &mut State, ctx: &mut Rltk) -> (ItemMenuResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let items = backpack_stacks(&gs.ecs, player_entity);
+    item_list_menu(ctx, "Throw Which Item?", &items)
+}
+
+/// Lists what is inside a chest so the player can take things out of it.
+pub fn show_container(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    container: Entity,
+) -> (ItemMenuResult, Option<Entity>) {
+    let title = match gs.ecs.read_storage::<Name>().get(container) {
+        Some(name) => format!("Take from the {}", name.name),
+        None => "Take Which Item?".to_string(),
+    };
+    let items = container_stacks(&gs.ecs, container);
+    let result = item_list_menu(ctx, &title, &items);
+    if items.is_empty() {
+        ctx.print_color(
             18,
-            y,
-            RGB::named(rltk::YELLOW),
+            24,
+            RGB::named(rltk::WHITE),
             RGB::named(rltk::BLACK),
-            97 + j as u8,
+            "It's empty.",
         );
-        ctx.set(
-            19,
-            y,
-            RGB::named(rltk::WHITE),
+    }
+    result
+}
+
+/// The buy/sell screen for a merchant. Buying lists the vendor's stock at full
+/// price; selling lists the player's backpack at half price. SPACE swaps between them.
+pub fn show_vendor(
+    gs: &mut State,
+    ctx: &mut Rltk,
+    vendor: Entity,
+    mode: VendorMode,
+) -> (VendorResult, Option<Entity>) {
+    let player_entity = *gs.ecs.fetch::<Entity>();
+    let (owner, title) = match mode {
+        VendorMode::Buy => (vendor, "Buy (SPACE to sell)"),
+        VendorMode::Sell => (player_entity, "Sell (SPACE to buy)"),
+    };
+
+    let items: Vec<(String, Entity)> = {
+        let values = gs.ecs.read_storage::<Value>();
+        backpack_stacks(&gs.ecs, owner)
+            .into_iter()
+            .map(|(name, entity)| {
+                let price = item_price(values.get(entity), mode);
+                (format!("{} - {} gp", name, price), entity)
+            })
+            .collect()
+    };
+
+    if ctx.key == Some(VirtualKeyCode::Space) {
+        return (VendorResult::Switch, None);
+    }
+    let result = item_list_menu(ctx, title, &items);
+
+    // Buying, the player wants to know what they can spend; selling, what the merchant can pay
+    let wallets = gs.ecs.read_storage::<Wallet>();
+    let purse = match mode {
+        VendorMode::Buy => wallets.get(player_entity).map(|w| format!("{} gp", w.gold)),
+        VendorMode::Sell => wallets.get(vendor).map(|w| format!("Merchant has {} gp", w.gold)),
+    };
+    if let Some(purse) = purse {
+        ctx.print_color(
+            40,
+            (25 - (items.len() / 2)) as i32 - 2,
+            RGB::named(rltk::GOLD),
             RGB::named(rltk::BLACK),
-            rltk::to_cp437(')'),
+            &purse,
         );
+    }
+
+    match result.0 {
+        ItemMenuResult::Cancel => (VendorResult::Cancel, None),
+        ItemMenuResult::NoResponse => (VendorResult::NoResponse, None),
+        ItemMenuResult::Selected => (VendorResult::Selected, result.1),
+    }
+}
 
-        ctx.print(21, y, &name.name.to_string());
-        equippable.push(entity);
-        y += 1;
-        j += 1;
+/// What a merchant charges for an item, or pays for it: they only give half value.
+pub fn item_price(value: Option<&Value>, mode: VendorMode) -> i32 {
+    let value = value.map_or(0, |v| v.gold);
+    match mode {
+        VendorMode::Buy => value,
+        VendorMode::Sell => value / 2,
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum AllyMenuResult {
+    Cancel,
+    NoResponse,
+    Follow,
+    Stay,
+    Attack,
+}
+
+/// Asks what the player wants their allies to do.
+pub fn ally_command_menu(ctx: &mut Rltk) -> AllyMenuResult {
+    let options = [
+        ('f', "Follow me"),
+        ('s', "Stay here"),
+        ('a', "Attack a target"),
+    ];
+    let top = 25 - (options.len() / 2) as i32;
+    ctx.draw_box(15, top - 2, 40, (options.len() + 3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(18, top - 2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Command Allies");
+    ctx.print_color(18, top + options.len() as i32 + 1, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ESCAPE to cancel");
+
+    for (y, (key, label)) in (top..).zip(options.iter()) {
+        ctx.set(17, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437('('));
+        ctx.set(18, y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), rltk::to_cp437(*key));
+        ctx.set(19, y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), rltk::to_cp437(')'));
+        ctx.print(21, y, label);
     }
 
     match ctx.key {
-        None => (ItemMenuResult::NoResponse, None),
+        None => AllyMenuResult::NoResponse,
         Some(key) => match key {
-            VirtualKeyCode::Escape => (ItemMenuResult::Cancel, None),
-            _ => {
-                let selection = rltk::letter_to_option(key);
-                if selection > -1 && selection < count as i32 {
-                    return (
-                        ItemMenuResult::Selected,
-                        Some(equippable[selection as usize]),
-                    );
-                }
-                (ItemMenuResult::NoResponse, None)
-            }
+            VirtualKeyCode::Escape => AllyMenuResult::Cancel,
+            VirtualKeyCode::F => AllyMenuResult::Follow,
+            VirtualKeyCode::S => AllyMenuResult::Stay,
+            VirtualKeyCode::A => AllyMenuResult::Attack,
+            _ => AllyMenuResult::NoResponse,
         },
     }
 }
@@ -368,3 +571,167 @@ pub fn ranged_target(gs : &mut State, ctx : &mut Rltk, range : i32) -> (ItemMenu
 
     (ItemMenuResult::NoResponse, None)
 }
+
+/// Game-wide settings, kept from one game to the next.
+pub struct Options {
+    pub particles: bool,
+    pub scanlines: bool,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options { particles: true, scanlines: true }
+    }
+}
+
+/// Where the mouse was when a menu was last drawn. Hovering only moves the selection
+/// once the mouse itself moves, so it doesn't fight with the keyboard.
+#[derive(Default)]
+pub struct MenuMouse {
+    pub last_pos: (i32, i32),
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MenuResult<T> {
+    NoResponse { selection: usize },
+    Cancel,
+    Selected(T),
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MainMenuSelection {
+    NewGame,
+    Continue,
+    Options,
+    Quit,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum PauseMenuSelection {
+    Resume,
+    Options,
+    MainMenu,
+    Quit,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum OptionsSelection {
+    Particles,
+    Scanlines,
+    Back,
+}
+
+/// A column of entries, picked with the arrow keys and Enter or by clicking on one.
+fn choice_menu<T: Copy>(ecs: &World, ctx: &mut Rltk, title: &str, entries: &[(T, String)], selection: usize) -> MenuResult<T> {
+    let width = entries.iter().map(|e| e.1.len()).max().unwrap_or(0).max(title.len()) as i32 + 4;
+    let x = 40 - width / 2;
+    let top = 25 - (entries.len() / 2) as i32;
+    ctx.draw_box(x - 1, top - 2, width + 1, (entries.len() + 3) as i32, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color(x + 1, top - 2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), title);
+
+    let mut selection = selection.min(entries.len() - 1);
+    let mouse_pos = ctx.mouse_pos();
+    let mut menu_mouse = ecs.write_resource::<MenuMouse>();
+    let mouse_moved = menu_mouse.last_pos != mouse_pos;
+    menu_mouse.last_pos = mouse_pos;
+    let mut clicked = false;
+    for (i, (y, (_choice, label))) in (top..).zip(entries.iter()).enumerate() {
+        if mouse_pos.1 == y && mouse_pos.0 >= x && mouse_pos.0 < x + width && (mouse_moved || ctx.left_click) {
+            selection = i;
+            clicked = ctx.left_click;
+        }
+        let fg = if i == selection { RGB::named(rltk::MAGENTA) } else { RGB::named(rltk::WHITE) };
+        ctx.print_color(x + 1, y, fg, RGB::named(rltk::BLACK), label);
+    }
+
+    if clicked {
+        return MenuResult::Selected(entries[selection].0);
+    }
+    match ctx.key {
+        None => MenuResult::NoResponse { selection },
+        Some(key) => match key {
+            VirtualKeyCode::Escape => MenuResult::Cancel,
+            VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
+                MenuResult::NoResponse { selection: (selection + entries.len() - 1) % entries.len() }
+            }
+            VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
+                MenuResult::NoResponse { selection: (selection + 1) % entries.len() }
+            }
+            VirtualKeyCode::Return | VirtualKeyCode::Space => MenuResult::Selected(entries[selection].0),
+            _ => MenuResult::NoResponse { selection },
+        },
+    }
+}
+
+/// The title screen. Continue is only offered when there's a game in progress to go back to.
+pub fn main_menu(ecs: &World, ctx: &mut Rltk, selection: usize, can_continue: bool) -> MenuResult<MainMenuSelection> {
+    ctx.print_color_centered(12, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Rustlike");
+    ctx.print_color_centered(14, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), "Use the arrow keys and Enter, or the mouse");
+
+    let mut entries = vec![(MainMenuSelection::NewGame, "New Game".to_string())];
+    if can_continue {
+        entries.push((MainMenuSelection::Continue, "Continue".to_string()));
+    }
+    entries.push((MainMenuSelection::Options, "Options".to_string()));
+    entries.push((MainMenuSelection::Quit, "Quit".to_string()));
+    choice_menu(ecs, ctx, "Main Menu", &entries, selection)
+}
+
+pub fn pause_menu(ecs: &World, ctx: &mut Rltk, selection: usize) -> MenuResult<PauseMenuSelection> {
+    let entries = [
+        (PauseMenuSelection::Resume, "Resume".to_string()),
+        (PauseMenuSelection::Options, "Options".to_string()),
+        (PauseMenuSelection::MainMenu, "Main Menu".to_string()),
+        (PauseMenuSelection::Quit, "Quit".to_string()),
+    ];
+    choice_menu(ecs, ctx, "Paused", &entries, selection)
+}
+
+pub fn options_menu(ecs: &World, ctx: &mut Rltk, selection: usize) -> MenuResult<OptionsSelection> {
+    let options = ecs.fetch::<Options>();
+    let on_off = |on: bool| if on { "On" } else { "Off" };
+    let entries = [
+        (OptionsSelection::Particles, format!("Particle effects: {}", on_off(options.particles))),
+        (OptionsSelection::Scanlines, format!("Scanlines: {}", on_off(options.scanlines))),
+        (OptionsSelection::Back, "Back".to_string()),
+    ];
+    choice_menu(ecs, ctx, "Options", &entries, selection)
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum GameOverResult {
+    NoResponse,
+    NewGame,
+    MainMenu,
+}
+
+/// How the player died and what they managed first.
+pub fn game_over(ecs: &World, ctx: &mut Rltk) -> GameOverResult {
+    let stats = ecs.fetch::<GameStats>();
+    let map = ecs.fetch::<Map>();
+    let cause = stats.cause_of_death.clone().unwrap_or_else(|| "Died".to_string());
+    let lines = [
+        cause,
+        format!("Survived for {} turns", stats.turns),
+        format!("Reached depth {}", map.depth),
+        format!("Killed {} monsters", stats.kills),
+    ];
+
+    ctx.draw_box(15, 16, 50, 14, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+    ctx.print_color_centered(16, RGB::named(rltk::RED), RGB::named(rltk::BLACK), "You are dead!");
+    let mut y = 19;
+    for line in lines.iter() {
+        ctx.print_color_centered(y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), line);
+        y += 2;
+    }
+    ctx.print_color_centered(28, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "ENTER for a new game, ESCAPE for the menu");
+
+    if ctx.left_click {
+        return GameOverResult::NewGame;
+    }
+    match ctx.key {
+        Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::Space) => GameOverResult::NewGame,
+        Some(VirtualKeyCode::Escape) => GameOverResult::MainMenu,
+        _ => GameOverResult::NoResponse,
+    }
+}
@@ -0,0 +1,81 @@
+extern crate rltk;
+use rltk::RandomNumberGenerator;
+use std::collections::{HashMap, HashSet};
+
+/// The kinds of magic item that turn up in disguise until the player identifies them.
+#[derive(PartialEq, Copy, Clone)]
+pub enum MagicKind {
+    Scroll,
+    Potion,
+}
+
+const POTION_LOOKS: &[&str] = &[
+    "Murky", "Fizzy", "Bubbling", "Cloudy", "Smoky", "Glowing", "Oily", "Milky", "Viscous",
+];
+
+const SYLLABLES: &[&str] = &[
+    "xy", "zzy", "ka", "lo", "mor", "pha", "ne", "ul", "tra", "vek", "qua", "ish", "om",
+];
+
+/// Per-run mapping from magic items' real names to their disguised appearances,
+/// plus the set of items the player has identified so far.
+#[derive(Default)]
+pub struct Identification {
+    pub appearances: HashMap<String, String>,
+    pub identified: HashSet<String>,
+}
+
+impl Identification {
+    /// Gives the named item type a random appearance, the first time one is made this
+    /// run. Scrolls get a unique nonsense label, and potions a look nothing else has.
+    pub fn disguise(&mut self, rng: &mut RandomNumberGenerator, name: &str, kind: MagicKind) {
+        if self.appearances.contains_key(name) {
+            return;
+        }
+
+        let appearance = match kind {
+            MagicKind::Scroll => {
+                let mut label = String::new();
+                loop {
+                    label.clear();
+                    for _i in 0..rng.range(2, 4) {
+                        label += rng.random_slice_entry(SYLLABLES).unwrap();
+                    }
+                    label = label.to_uppercase();
+                    if !self.appearances.values().any(|a| a.ends_with(&label)) {
+                        break;
+                    }
+                }
+                format!("Scroll labelled {}", label)
+            }
+            MagicKind::Potion => {
+                let looks: Vec<String> = POTION_LOOKS
+                    .iter()
+                    .map(|look| format!("{} Potion", look))
+                    .filter(|look| !self.appearances.values().any(|a| a == look))
+                    .collect();
+                match rng.random_slice_entry(&looks) {
+                    Some(look) => look.to_string(),
+                    None => "Strange Potion".to_string(),
+                }
+            }
+        };
+        self.appearances.insert(name.to_string(), appearance);
+    }
+
+    /// The name the player should see for something with the given real name.
+    pub fn display_name(&self, name: &str) -> String {
+        if self.identified.contains(name) {
+            return name.to_string();
+        }
+        match self.appearances.get(name) {
+            Some(appearance) => appearance.to_string(),
+            None => name.to_string(),
+        }
+    }
+
+    /// Marks an item type as known. Returns true if it was not known before.
+    pub fn identify(&mut self, name: &str) -> bool {
+        self.appearances.contains_key(name) && self.identified.insert(name.to_string())
+    }
+}
@@ -1,10 +1,106 @@
 extern crate specs;
 use super::{
-    gamelog::GameLog, AreaOfEffect, CombatStats, Consumable, InBackpack, InflictsDamage, Map, Name,
-    Position, ProvidesHealing, SufferDamage, WantsToDropItem, WantsToPickupItem, WantsToUseItem,
-    Confusion
+    faction,
+    gamelog::GameLog,
+    gui::{item_price, VendorMode},
+    identification::Identification,
+    particle_system::ParticleBuilder,
+    status_system::apply_status,
+    AiState, Ally, AllyCommand, AreaOfEffect, Brain, Buc, BucStatus, Burdened, Charges, Charms,
+    CombatStats, Consumable, Faction, Fragile, Gold, IdentifiesItems, InBackpack, InContainer,
+    InflictsDamage, InflictsStatus, MagicMapper, Map, Name, Position, ProvidesHealing,
+    RemovesCurse, RunState, Spell, StatusEffect, StatusEffects, StatusKind, SufferDamage,
+    Teleports, TileType, Unique, Value, Viewshed, Wallet, WantsToDropItem, WantsToPickupItem,
+    WantsToThrowItem, WantsToUseItem, Weight,
 };
+use rltk::{Point, RandomNumberGenerator, RGB};
 use specs::prelude::*;
+use specs::storage::MaskedStorage;
+use std::ops::Deref;
+
+/// How far anything can be thrown.
+pub const THROW_RANGE: i32 = 6;
+
+/// How much an entity can carry before it becomes burdened.
+pub fn carry_capacity(stats: &CombatStats) -> i32 {
+    10 + stats.power * 3
+}
+
+/// Nothing can be picked up if it would take the carrier past this.
+pub fn max_carry_weight(stats: &CombatStats) -> i32 {
+    carry_capacity(stats) * 3 / 2
+}
+
+/// Total weight of everything in an entity's backpack, for systems that already hold
+/// the storages.
+pub fn pack_weight<B, W>(
+    backpack: &Storage<InBackpack, B>,
+    weights: &Storage<Weight, W>,
+    owner: Entity,
+) -> i32
+where
+    B: Deref<Target = MaskedStorage<InBackpack>>,
+    W: Deref<Target = MaskedStorage<Weight>>,
+{
+    (backpack, weights)
+        .join()
+        .filter(|item| item.0.owner == owner)
+        .map(|item| item.1.weight)
+        .sum()
+}
+
+/// Total weight of everything in an entity's backpack.
+pub fn carried_weight(ecs: &World, owner: Entity) -> i32 {
+    pack_weight(
+        &ecs.read_storage::<InBackpack>(),
+        &ecs.read_storage::<Weight>(),
+        owner,
+    )
+}
+
+/// True if an item of `item_weight` would fit on top of what's already `carried`.
+pub fn has_room(stats: &CombatStats, carried: i32, item_weight: i32) -> bool {
+    carried + item_weight <= max_carry_weight(stats)
+}
+
+/// True if the carrier has room in their pack for one more item.
+pub fn can_carry(ecs: &World, carrier: Entity, item: Entity) -> bool {
+    let weights = ecs.read_storage::<Weight>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    match combat_stats.get(carrier) {
+        None => true,
+        Some(stats) => {
+            let item_weight = weights.get(item).map_or(0, |w| w.weight);
+            has_room(stats, carried_weight(ecs, carrier), item_weight)
+        }
+    }
+}
+
+/// How a blessing or curse reads in menus and the log.
+pub fn buc_name(status: BucStatus) -> &'static str {
+    match status {
+        BucStatus::Blessed => "Blessed",
+        BucStatus::Uncursed => "Uncursed",
+        BucStatus::Cursed => "Cursed",
+    }
+}
+
+/// The status an item really inflicts once its blessing or curse is taken into account:
+/// curses may invert it, and otherwise change how long it lasts.
+fn item_status_effect(status: &InflictsStatus, buc: BucStatus, source: Entity) -> StatusEffect {
+    let kind = buc.status_kind(status.kind);
+    let turns = if kind == status.kind {
+        buc.scale(status.turns)
+    } else {
+        status.turns
+    };
+    StatusEffect {
+        kind,
+        turns,
+        potency: status.potency,
+        source: Some(source),
+    }
+}
 
 pub struct ItemCollectionSystem {}
 
@@ -13,17 +109,65 @@ impl<'a> System<'a> for ItemCollectionSystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        ReadExpect<'a, Identification>,
         WriteStorage<'a, WantsToPickupItem>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Name>,
         WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, CombatStats>,
+        ReadExpect<'a, Map>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (player_entity, mut gamelog, mut wants_pickup, mut positions, names, mut backpack) =
-            data;
+        let (
+            player_entity,
+            mut gamelog,
+            identification,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            weights,
+            combat_stats,
+            map,
+        ) = data;
 
         for pickup in wants_pickup.join() {
+            // Refuse anything that would go over the carrier's limit
+            if let Some(stats) = combat_stats.get(pickup.collected_by) {
+                let carried = pack_weight(&backpack, &weights, pickup.collected_by);
+                let item_weight = weights.get(pickup.item).map_or(0, |w| w.weight);
+                if !has_room(stats, carried, item_weight) {
+                    if pickup.collected_by == *player_entity {
+                        gamelog.entries.insert(
+                            0,
+                            format!(
+                                "You can't carry the {}: your pack is full.",
+                                identification.display_name(&names.get(pickup.item).unwrap().name)
+                            ),
+                        );
+                    }
+                    continue;
+                }
+            }
+
+            // Let the player see anyone else picking things up
+            if pickup.collected_by != *player_entity {
+                if let Some(pos) = positions.get(pickup.collected_by) {
+                    if map.visible_tiles[map.xy_idx(pos.x, pos.y)] {
+                        gamelog.entries.insert(
+                            0,
+                            format!(
+                                "{} picks up a {}.",
+                                names.get(pickup.collected_by).unwrap().name,
+                                identification.display_name(&names.get(pickup.item).unwrap().name)
+                            ),
+                        );
+                    }
+                }
+            }
+
             // Look at all WantsToPickupItem Components
             positions.remove(pickup.item); // Remove the pickup's position component
             backpack
@@ -40,7 +184,7 @@ impl<'a> System<'a> for ItemCollectionSystem {
                     0,
                     format!(
                         "Added: {} to your backpack.",
-                        names.get(pickup.item).unwrap().name
+                        identification.display_name(&names.get(pickup.item).unwrap().name)
                     ),
                 );
             }
@@ -57,6 +201,7 @@ impl<'a> System<'a> for ItemUseSystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, Identification>,
         ReadExpect<'a, Map>,
         Entities<'a>,
         WriteStorage<'a, WantsToUseItem>,
@@ -64,16 +209,43 @@ impl<'a> System<'a> for ItemUseSystem {
         ReadStorage<'a, Consumable>,
         ReadStorage<'a, ProvidesHealing>,
         ReadStorage<'a, InflictsDamage>,
-        WriteStorage<'a, Confusion>,
+        ReadStorage<'a, InflictsStatus>,
+        WriteStorage<'a, StatusEffects>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
         ReadStorage<'a, AreaOfEffect>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, IdentifiesItems>,
+        ReadStorage<'a, InBackpack>,
+        WriteStorage<'a, Charges>,
+        (
+            ReadStorage<'a, MagicMapper>,
+            ReadStorage<'a, Teleports>,
+            ReadStorage<'a, RemovesCurse>,
+            ReadStorage<'a, Spell>,
+            ReadStorage<'a, Charms>,
+        ),
+        (
+            WriteStorage<'a, Faction>,
+            WriteStorage<'a, Ally>,
+            WriteStorage<'a, Brain>,
+            ReadStorage<'a, Unique>,
+        ),
+        WriteStorage<'a, Buc>,
+        WriteStorage<'a, Position>,
+        (
+            WriteExpect<'a, Point>,
+            WriteExpect<'a, RunState>,
+            WriteExpect<'a, RandomNumberGenerator>,
+        ),
+        WriteExpect<'a, ParticleBuilder>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             player_entity,
             mut gamelog,
+            mut identification,
             map,
             entities,
             mut wants_use,
@@ -81,22 +253,157 @@ impl<'a> System<'a> for ItemUseSystem {
             consumables,
             healing,
             inflict_damage,
-            mut confused,
+            inflict_status,
+            mut statuses,
             mut combat_stats,
             mut suffer_damage,
             aoe,
+            mut viewsheds,
+            identifies,
+            backpack,
+            mut charges,
+            (magic_mapper, teleports, removes_curse, spells, charms),
+            (mut factions, mut allies, mut brains, uniques),
+            mut bucs,
+            mut positions,
+            (mut player_pos, mut runstate, mut rng),
+            mut particles,
         ) = data;
 
         for (entity, useitem) in (&entities, &wants_use).join() {
             let mut used_item = true;
 
+            // An empty wand does nothing at all
+            if let Some(charges) = charges.get(useitem.item) {
+                if charges.remaining < 1 {
+                    if entity == *player_entity {
+                        gamelog
+                            .entries
+                            .insert(0, "You wave it around, but nothing happens.".to_string());
+                    }
+                    continue;
+                }
+            }
+
+            // Using an item reveals what it really is
+            if entity == *player_entity {
+                let item_name = &names.get(useitem.item).unwrap().name;
+                let appearance = identification.display_name(item_name);
+                if identification.identify(item_name) {
+                    gamelog
+                        .entries
+                        .insert(0, format!("The {} was a {}!", appearance, item_name));
+                }
+            }
+
+            // Blessings and curses change how well the item works, and show themselves on use
+            let buc = bucs
+                .get(useitem.item)
+                .map_or(BucStatus::Uncursed, |b| b.status);
+            if entity == *player_entity {
+                if let Some(item_buc) = bucs.get_mut(useitem.item) {
+                    if !item_buc.known && item_buc.status != BucStatus::Uncursed {
+                        gamelog.entries.insert(
+                            0,
+                            format!(
+                                "You sense that it was {}.",
+                                buc_name(item_buc.status).to_lowercase()
+                            ),
+                        );
+                    }
+                    item_buc.known = true;
+                }
+            }
+
+            // Identify everything else the player is carrying
+            if identifies.get(useitem.item).is_some() && entity == *player_entity {
+                let mut revealed = Vec::new();
+                for (carried, _pack) in (&entities, &backpack)
+                    .join()
+                    .filter(|item| item.1.owner == entity && item.0 != useitem.item)
+                {
+                    let carried_name = &names.get(carried).unwrap().name;
+                    let appearance = identification.display_name(carried_name);
+                    if identification.identify(carried_name) {
+                        revealed.push(format!("The {} is a {}.", appearance, carried_name));
+                    }
+                    if let Some(carried_buc) = bucs.get_mut(carried) {
+                        carried_buc.known = true;
+                    }
+                }
+                if revealed.is_empty() {
+                    gamelog
+                        .entries
+                        .insert(0, "You have nothing left to identify.".to_string());
+                }
+                for line in revealed {
+                    gamelog.entries.insert(0, line);
+                }
+            }
+
+            // Lift curses from everything the user carries
+            if removes_curse.get(useitem.item).is_some() {
+                let mut uncursed = 0;
+                for (_carried, _pack, carried_buc) in (&entities, &backpack, &mut bucs)
+                    .join()
+                    .filter(|item| item.1.owner == entity && item.0 != useitem.item)
+                {
+                    if carried_buc.status == BucStatus::Cursed {
+                        carried_buc.status = BucStatus::Uncursed;
+                        uncursed += 1;
+                    }
+                    carried_buc.known = true;
+                }
+                if entity == *player_entity {
+                    let message = if uncursed > 0 {
+                        "You feel as if someone is watching over you."
+                    } else {
+                        "You feel a faint warmth, but nothing more."
+                    };
+                    gamelog.entries.insert(0, message.to_string());
+                }
+            }
+
+            // Let the player know when something else casts a spell or uses an item in view
+            if entity != *player_entity {
+                if let Some(pos) = positions.get(entity) {
+                    if map.visible_tiles[map.xy_idx(pos.x, pos.y)] {
+                        let item_name = &names.get(useitem.item).unwrap().name;
+                        let message = if spells.get(useitem.item).is_some() {
+                            format!("{} casts {}.", names.get(entity).unwrap().name, item_name)
+                        } else {
+                            format!(
+                                "{} uses a {}.",
+                                names.get(entity).unwrap().name,
+                                identification.display_name(item_name)
+                            )
+                        };
+                        gamelog.entries.insert(0, message);
+                    }
+                }
+            }
+
             // Targeting
             let mut targets: Vec<Entity> = Vec::new();
             match useitem.target {
                 None => {
-                    targets.push(*player_entity);
+                    targets.push(entity);
                 }
                 Some(target) => {
+                    // Anything aimed at someone leaves a trail on its way there
+                    let aimed = inflict_damage.get(useitem.item).is_some()
+                        || inflict_status.get(useitem.item).is_some()
+                        || charms.get(useitem.item).is_some();
+                    if let (true, Some(pos)) = (aimed, positions.get(entity)) {
+                        particles.trail(
+                            Point::new(pos.x, pos.y),
+                            target,
+                            RGB::named(rltk::CYAN),
+                            rltk::to_cp437('*'),
+                            150.0,
+                        );
+                    }
+
                     let area_effect = aoe.get(useitem.item);
                     match area_effect {
                         None => {
@@ -114,6 +421,13 @@ impl<'a> System<'a> for ItemUseSystem {
                                 p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1
                             });
                             for tile_idx in blast_tiles.iter() {
+                                particles.request(
+                                    tile_idx.x,
+                                    tile_idx.y,
+                                    RGB::named(rltk::ORANGE),
+                                    rltk::to_cp437('░'),
+                                    300.0,
+                                );
                                 let idx = map.xy_idx(tile_idx.x, tile_idx.y);
                                 for mob in map.tile_content[idx].iter() {
                                     targets.push(*mob);
@@ -124,23 +438,87 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
+            // Magic mapping reveals the whole level, a row at a time
+            if magic_mapper.get(useitem.item).is_some() && entity == *player_entity {
+                gamelog.entries.insert(
+                    0,
+                    "The layout of the level unfolds in your mind!".to_string(),
+                );
+                *runstate = RunState::MagicMapReveal { row: 0 };
+            }
+
+            // Teleportation sends the user where they aimed, or anywhere at all if they didn't
+            if teleports.get(useitem.item).is_some() {
+                let mut destination = None;
+                if let Some(target) = useitem.target {
+                    if !map.blocked[map.xy_idx(target.x, target.y)] {
+                        destination = Some(target);
+                    }
+                } else {
+                    for _attempt in 0..100 {
+                        let x = rng.roll_dice(1, map.width - 2);
+                        let y = rng.roll_dice(1, map.height - 2);
+                        if !map.blocked[map.xy_idx(x, y)] {
+                            destination = Some(Point::new(x, y));
+                            break;
+                        }
+                    }
+                }
+
+                match destination {
+                    None => {
+                        if entity == *player_entity {
+                            gamelog.entries.insert(
+                                0,
+                                "You feel a brief tug, but stay where you are.".to_string(),
+                            );
+                        }
+                    }
+                    Some(destination) => {
+                        if let Some(pos) = positions.get_mut(entity) {
+                            pos.x = destination.x;
+                            pos.y = destination.y;
+                        }
+                        if let Some(viewshed) = viewsheds.get_mut(entity) {
+                            viewshed.dirty = true;
+                        }
+                        if entity == *player_entity {
+                            *player_pos = destination;
+                            gamelog
+                                .entries
+                                .insert(0, "The world blurs around you.".to_string());
+                        }
+                    }
+                }
+            }
+
             // If it heals, apply the healing
             let item_heals = healing.get(useitem.item);
             match item_heals {
                 None => {}
                 Some(healer) => {
                     used_item = false;
+                    let heal_amount = buc.scale(healer.heal_amount);
                     for target in targets.iter() {
                         let stats = combat_stats.get_mut(*target);
                         if let Some(stats) = stats {
-                            stats.hp = i32::min(stats.max_hp, stats.hp + healer.heal_amount);
+                            stats.hp = i32::min(stats.max_hp, stats.hp + heal_amount);
+                            if let Some(pos) = positions.get(*target) {
+                                particles.request(
+                                    pos.x,
+                                    pos.y,
+                                    RGB::named(rltk::GREEN),
+                                    rltk::to_cp437('♥'),
+                                    200.0,
+                                );
+                            }
                             if entity == *player_entity {
                                 gamelog.entries.insert(
                                     0,
                                     format!(
                                         "You use the {}, healing {} hp.",
                                         names.get(useitem.item).unwrap().name,
-                                        healer.heal_amount
+                                        heal_amount
                                     ),
                                 );
                             }
@@ -156,15 +534,18 @@ impl<'a> System<'a> for ItemUseSystem {
                 None => {}
                 Some(damage) => {
                     used_item = false;
-                    for mob in targets.iter() {
-                        suffer_damage
-                            .insert(
-                                *mob,
-                                SufferDamage {
-                                    amount: damage.damage,
-                                },
-                            )
-                            .expect("Unable to insert");
+                    let damage = buc.scale(damage.damage);
+                    for mob in targets.iter().filter(|t| combat_stats.get(**t).is_some()) {
+                        SufferDamage::new_damage(&mut suffer_damage, *mob, damage, entity);
+                        if let Some(pos) = positions.get(*mob) {
+                            particles.request(
+                                pos.x,
+                                pos.y,
+                                RGB::named(rltk::RED),
+                                rltk::to_cp437('‼'),
+                                200.0,
+                            );
+                        }
                         if entity == *player_entity {
                             let mob_name = names.get(*mob).unwrap();
                             let item_name = names.get(useitem.item).unwrap();
@@ -172,7 +553,17 @@ impl<'a> System<'a> for ItemUseSystem {
                                 0,
                                 format!(
                                     "You use {} on {}, inflicting {} hp.",
-                                    item_name.name, mob_name.name, damage.damage
+                                    item_name.name, mob_name.name, damage
+                                ),
+                            );
+                        } else if *mob == *player_entity {
+                            gamelog.entries.insert(
+                                0,
+                                format!(
+                                    "The {} hits you for {} hp!",
+                                    identification
+                                        .display_name(&names.get(useitem.item).unwrap().name),
+                                    damage
                                 ),
                             );
                         }
@@ -182,35 +573,125 @@ impl<'a> System<'a> for ItemUseSystem {
                 }
             }
 
-            // Can it pass along confusion? Note the use of scopes to escape from the borrow checker!
-            let mut add_confusion = Vec::new();
-            {
-                let causes_confusion = confused.get(useitem.item);
-                match causes_confusion {
-                    None => {}
-                    Some(confusion) => {
-                        used_item = false;
-                        for mob in targets.iter() {
-                            add_confusion.push((*mob, confusion.turns));
-                            if entity == *player_entity {
-                                let mob_name = names.get(*mob).unwrap();
-                                let item_name = names.get(useitem.item).unwrap();
-                                gamelog.entries.insert(
-                                    0,
-                                    format!(
-                                        "You use {} on {}, confusing them.",
-                                        item_name.name, mob_name.name
-                                    ),
-                                );
+            // Can it pass along a status effect?
+            let item_status = inflict_status.get(useitem.item);
+            match item_status {
+                None => {}
+                Some(status) => {
+                    used_item = false;
+                    let effect = item_status_effect(status, buc, entity);
+                    for mob in targets.iter().filter(|t| combat_stats.get(**t).is_some()) {
+                        apply_status(&mut statuses, *mob, effect);
+                        if let Some(pos) = positions.get(*mob) {
+                            let glyph = if effect.kind == StatusKind::Confusion {
+                                '?'
+                            } else {
+                                '*'
+                            };
+                            particles.request(
+                                pos.x,
+                                pos.y,
+                                RGB::named(rltk::MAGENTA),
+                                rltk::to_cp437(glyph),
+                                200.0,
+                            );
+                        }
+                        if effect.kind == StatusKind::Blindness {
+                            if let Some(viewshed) = viewsheds.get_mut(*mob) {
+                                viewshed.dirty = true;
                             }
                         }
+                        if entity == *player_entity {
+                            let mob_name = names.get(*mob).unwrap();
+                            let item_name = names.get(useitem.item).unwrap();
+                            gamelog.entries.insert(
+                                0,
+                                format!(
+                                    "You use {} on {}. {} is now {}.",
+                                    item_name.name,
+                                    mob_name.name,
+                                    mob_name.name,
+                                    effect.kind.name().to_lowercase()
+                                ),
+                            );
+                        } else if *mob == *player_entity {
+                            gamelog.entries.insert(
+                                0,
+                                format!("You are now {}!", effect.kind.name().to_lowercase()),
+                            );
+                        }
+                        used_item = true;
                     }
                 }
             }
-            for mob in add_confusion.iter() {
-                confused
-                    .insert(mob.0, Confusion { turns: mob.1 })
-                    .expect("Unable to insert status");
+
+            // Charming brings creatures over to the user's side, though uniques are too strong-willed
+            if charms.get(useitem.item).is_some() {
+                used_item = false;
+                let side = factions
+                    .get(entity)
+                    .map_or(faction::PLAYER.to_string(), |f| f.name.clone());
+                for mob in targets.iter().filter(|t| **t != entity) {
+                    let brain = match brains.get_mut(*mob) {
+                        Some(brain) => brain,
+                        None => continue,
+                    };
+                    let mob_name = &names.get(*mob).unwrap().name;
+                    used_item = true;
+                    if uniques.get(*mob).is_some() {
+                        if entity == *player_entity {
+                            gamelog
+                                .entries
+                                .insert(0, format!("{} shrugs off the charm.", mob_name));
+                        }
+                        continue;
+                    }
+
+                    factions
+                        .insert(*mob, Faction { name: side.clone() })
+                        .expect("Unable to insert faction");
+                    if let Some(pos) = positions.get(*mob) {
+                        particles.request(
+                            pos.x,
+                            pos.y,
+                            RGB::named(rltk::PINK),
+                            rltk::to_cp437('♥'),
+                            200.0,
+                        );
+                    }
+                    brain.state = AiState::Idle;
+                    brain.target = None;
+                    brain.last_seen = None;
+                    if entity == *player_entity {
+                        allies
+                            .insert(
+                                *mob,
+                                Ally {
+                                    command: AllyCommand::Follow,
+                                },
+                            )
+                            .expect("Unable to insert ally");
+                        gamelog
+                            .entries
+                            .insert(0, format!("{} is now your friend.", mob_name));
+                    }
+                }
+            }
+
+            // Items with charges use one up, and are left empty rather than destroyed
+            if used_item {
+                if let Some(charges) = charges.get_mut(useitem.item) {
+                    charges.remaining -= 1;
+                    if charges.remaining < 1 && entity == *player_entity {
+                        gamelog.entries.insert(
+                            0,
+                            format!(
+                                "Your {} fizzles and goes dark.",
+                                identification.display_name(&names.get(useitem.item).unwrap().name)
+                            ),
+                        );
+                    }
+                }
             }
 
             // If its a consumable, we delete it on use
@@ -236,6 +717,7 @@ impl<'a> System<'a> for ItemDropSystem {
     type SystemData = (
         ReadExpect<'a, Entity>,
         WriteExpect<'a, GameLog>,
+        ReadExpect<'a, Identification>,
         Entities<'a>,
         WriteStorage<'a, WantsToDropItem>,
         ReadStorage<'a, Name>,
@@ -247,6 +729,7 @@ impl<'a> System<'a> for ItemDropSystem {
         let (
             player_entity,
             mut gamelog,
+            identification,
             entities,
             mut wants_drop,
             names,
@@ -275,7 +758,10 @@ impl<'a> System<'a> for ItemDropSystem {
             if entity == *player_entity {
                 gamelog.entries.insert(
                     0,
-                    format!("You drop up the {}.", names.get(to_drop.item).unwrap().name),
+                    format!(
+                        "You drop up the {}.",
+                        identification.display_name(&names.get(to_drop.item).unwrap().name)
+                    ),
                 );
             }
         }
@@ -283,3 +769,375 @@ impl<'a> System<'a> for ItemDropSystem {
         wants_drop.clear();
     }
 }
+
+/// Works out how loaded down everyone is, and marks anyone over their capacity as burdened.
+pub struct EncumbranceSystem {}
+
+impl<'a> System<'a> for EncumbranceSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        ReadStorage<'a, InBackpack>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, CombatStats>,
+        WriteStorage<'a, Burdened>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut gamelog, entities, backpack, weights, combat_stats, mut burdened) =
+            data;
+
+        for (entity, stats) in (&entities, &combat_stats).join() {
+            let overloaded = pack_weight(&backpack, &weights, entity) > carry_capacity(stats);
+            let was_burdened = burdened.get(entity).is_some();
+
+            if overloaded && !was_burdened {
+                burdened
+                    .insert(entity, Burdened {})
+                    .expect("Unable to insert burden");
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .insert(0, "You are burdened by your pack.".to_string());
+                }
+            } else if !overloaded && was_burdened {
+                burdened.remove(entity);
+                if entity == *player_entity {
+                    gamelog
+                        .entries
+                        .insert(0, "You are no longer burdened.".to_string());
+                }
+            }
+        }
+    }
+}
+
+/// Anyone with a wallet scoops up gold piles they walk over.
+pub struct GoldCollectionSystem {}
+
+impl<'a> System<'a> for GoldCollectionSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, Wallet>,
+        ReadStorage<'a, Gold>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (player_entity, mut gamelog, map, entities, positions, mut wallets, gold) = data;
+
+        for (entity, pos, wallet) in (&entities, &positions, &mut wallets).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            for pile in map.tile_content[idx].iter() {
+                if let Some(gold) = gold.get(*pile) {
+                    wallet.gold += gold.amount;
+                    entities.delete(*pile).expect("Delete failed");
+                    if entity == *player_entity {
+                        gamelog
+                            .entries
+                            .insert(0, format!("You pick up {} gold.", gold.amount));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Moves an item from a merchant's stock into the player's pack, if they can afford it and carry it.
+pub fn buy_item(ecs: &mut World, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let fits = can_carry(ecs, player_entity, item);
+    let mut gamelog = ecs.write_resource::<GameLog>();
+    let identification = ecs.fetch::<Identification>();
+    let names = ecs.read_storage::<Name>();
+    let values = ecs.read_storage::<Value>();
+    let mut wallets = ecs.write_storage::<Wallet>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+
+    let item_name = identification.display_name(&names.get(item).unwrap().name);
+    let price = item_price(values.get(item), VendorMode::Buy);
+    let wallet = match wallets.get_mut(player_entity) {
+        Some(wallet) => wallet,
+        None => return,
+    };
+    if wallet.gold < price {
+        gamelog
+            .entries
+            .insert(0, format!("You can't afford the {}.", item_name));
+        return;
+    }
+
+    if !fits {
+        gamelog.entries.insert(
+            0,
+            format!("You can't carry the {}: your pack is full.", item_name),
+        );
+        return;
+    }
+
+    wallet.gold -= price;
+    // The merchant pockets what the player paid, and can spend it buying from them later
+    if let Some(vendor) = backpack.get(item).map(|pack| pack.owner) {
+        if let Some(till) = wallets.get_mut(vendor) {
+            till.gold += price;
+        }
+    }
+    backpack
+        .insert(
+            item,
+            InBackpack {
+                owner: player_entity,
+            },
+        )
+        .expect("Unable to insert backpack entry");
+    gamelog
+        .entries
+        .insert(0, format!("You buy the {} for {} gp.", item_name, price));
+}
+
+/// Hands one of the player's items over to a merchant in exchange for half its value, as
+/// long as the merchant has that much gold left.
+pub fn sell_item(ecs: &mut World, vendor: Entity, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+    let identification = ecs.fetch::<Identification>();
+    let names = ecs.read_storage::<Name>();
+    let values = ecs.read_storage::<Value>();
+    let mut wallets = ecs.write_storage::<Wallet>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+
+    let item_name = identification.display_name(&names.get(item).unwrap().name);
+    let price = item_price(values.get(item), VendorMode::Sell);
+    match wallets.get_mut(vendor) {
+        Some(till) if till.gold >= price => till.gold -= price,
+        _ => {
+            gamelog.entries.insert(
+                0,
+                format!("The merchant can't afford to buy the {}.", item_name),
+            );
+            return;
+        }
+    }
+    if let Some(wallet) = wallets.get_mut(player_entity) {
+        wallet.gold += price;
+    }
+    backpack
+        .insert(item, InBackpack { owner: vendor })
+        .expect("Unable to insert backpack entry");
+    gamelog
+        .entries
+        .insert(0, format!("You sell the {} for {} gp.", item_name, price));
+}
+
+/// Moves an item out of a container and into the player's pack.
+pub fn take_from_container(ecs: &mut World, item: Entity) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let fits = can_carry(ecs, player_entity, item);
+    let mut gamelog = ecs.write_resource::<GameLog>();
+    let identification = ecs.fetch::<Identification>();
+    let names = ecs.read_storage::<Name>();
+    let mut in_container = ecs.write_storage::<InContainer>();
+    let mut backpack = ecs.write_storage::<InBackpack>();
+
+    let item_name = identification.display_name(&names.get(item).unwrap().name);
+    if !fits {
+        gamelog.entries.insert(
+            0,
+            format!("You can't carry the {}: your pack is full.", item_name),
+        );
+        return;
+    }
+
+    in_container.remove(item);
+    backpack
+        .insert(
+            item,
+            InBackpack {
+                owner: player_entity,
+            },
+        )
+        .expect("Unable to insert backpack entry");
+    gamelog
+        .entries
+        .insert(0, format!("You take the {}.", item_name));
+}
+
+/// Hurls items: they fly along a line until they hit a wall or a creature.
+/// Fragile items shatter and splash their effect on everything next to where
+/// they break; anything else hits for its weight and lands on the floor.
+pub struct ItemThrowSystem {}
+
+impl<'a> System<'a> for ItemThrowSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteExpect<'a, Identification>,
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToThrowItem>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, Weight>,
+        ReadStorage<'a, Fragile>,
+        ReadStorage<'a, ProvidesHealing>,
+        ReadStorage<'a, InflictsStatus>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Buc>,
+        WriteExpect<'a, ParticleBuilder>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            mut identification,
+            map,
+            entities,
+            mut wants_throw,
+            names,
+            mut positions,
+            mut backpack,
+            weights,
+            fragile,
+            healing,
+            inflict_status,
+            mut statuses,
+            mut combat_stats,
+            mut suffer_damage,
+            mut viewsheds,
+            bucs,
+            mut particles,
+        ) = data;
+
+        for (entity, throw) in (&entities, &wants_throw).join() {
+            let buc = bucs
+                .get(throw.item)
+                .map_or(BucStatus::Uncursed, |b| b.status);
+            let start = match positions.get(entity) {
+                Some(pos) => rltk::Point::new(pos.x, pos.y),
+                None => continue,
+            };
+            let is_player = entity == *player_entity;
+            let item_name = names.get(throw.item).unwrap().name.to_string();
+
+            // Follow the item's flight until something stops it
+            let mut landing = start;
+            let mut victim: Option<Entity> = None;
+            let flight = rltk::line2d(rltk::LineAlg::Bresenham, start, throw.target);
+            for point in flight.iter().filter(|p| **p != start) {
+                let idx = map.xy_idx(point.x, point.y);
+                if map.tiles[idx] == TileType::Wall {
+                    break;
+                }
+                landing = *point;
+                victim = map.tile_content[idx]
+                    .iter()
+                    .find(|e| combat_stats.get(**e).is_some())
+                    .copied();
+                if victim.is_some() {
+                    break;
+                }
+            }
+
+            backpack.remove(throw.item);
+            particles.trail(
+                start,
+                landing,
+                RGB::named(rltk::CYAN),
+                rltk::to_cp437('*'),
+                150.0,
+            );
+
+            if fragile.get(throw.item).is_some() {
+                // Seeing it break tells you what it was
+                if is_player {
+                    identification.identify(&item_name);
+                    gamelog.entries.insert(
+                        0,
+                        format!("The {} shatters!", identification.display_name(&item_name)),
+                    );
+                }
+                let mut splashed = rltk::field_of_view(landing, 1, &*map);
+                splashed
+                    .retain(|p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1);
+                for tile in splashed.iter() {
+                    particles.request(
+                        tile.x,
+                        tile.y,
+                        RGB::named(rltk::CYAN),
+                        rltk::to_cp437('░'),
+                        300.0,
+                    );
+                    let idx = map.xy_idx(tile.x, tile.y);
+                    for mob in map.tile_content[idx].iter() {
+                        if let Some(stats) = combat_stats.get_mut(*mob) {
+                            if let Some(heal) = healing.get(throw.item) {
+                                stats.hp =
+                                    i32::min(stats.max_hp, stats.hp + buc.scale(heal.heal_amount));
+                            }
+                            if let Some(status) = inflict_status.get(throw.item) {
+                                let effect = item_status_effect(status, buc, entity);
+                                apply_status(&mut statuses, *mob, effect);
+                                if effect.kind == StatusKind::Blindness {
+                                    if let Some(viewshed) = viewsheds.get_mut(*mob) {
+                                        viewshed.dirty = true;
+                                    }
+                                }
+                            }
+                            if is_player {
+                                gamelog.entries.insert(
+                                    0,
+                                    format!(
+                                        "{} is splashed by the {}.",
+                                        names.get(*mob).unwrap().name,
+                                        item_name
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                }
+                entities.delete(throw.item).expect("Delete failed");
+                continue;
+            }
+
+            if let Some(victim) = victim {
+                let damage = i32::max(1, weights.get(throw.item).map_or(0, |w| w.weight));
+                SufferDamage::new_damage(&mut suffer_damage, victim, damage, entity);
+                if is_player {
+                    gamelog.entries.insert(
+                        0,
+                        format!(
+                            "The {} hits {}, for {} hp.",
+                            identification.display_name(&item_name),
+                            names.get(victim).unwrap().name,
+                            damage
+                        ),
+                    );
+                }
+            }
+            positions
+                .insert(
+                    throw.item,
+                    Position {
+                        x: landing.x,
+                        y: landing.y,
+                    },
+                )
+                .expect("Unable to insert position");
+        }
+
+        wants_throw.clear();
+    }
+}
@@ -0,0 +1,52 @@
+extern crate specs;
+use super::{LightSource, Map, Player, Position, Viewshed};
+use rltk::{field_of_view, Point, RGB};
+use specs::prelude::*;
+
+/// How brightly a level is lit before any light sources are added, unless it's dark.
+const AMBIENT: f32 = 0.7;
+
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, LightSource>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Player>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, positions, lights, mut viewsheds, players) = data;
+
+        let ambient = if map.dark { 0.0 } else { AMBIENT };
+        let unlit = vec![RGB::from_f32(ambient, ambient, ambient); map.light.len()];
+        let previous = std::mem::replace(&mut map.light, unlit);
+
+        // Each light fades out towards the edge of its range, and doesn't shine through walls
+        for (pos, light) in (&positions, &lights).join() {
+            let centre = Point::new(pos.x, pos.y);
+            let lit_tiles = field_of_view(centre, light.range, &*map);
+            for tile in lit_tiles.iter() {
+                if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height {
+                    continue;
+                }
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(centre, *tile);
+                let intensity = 1.0 - distance / (light.range as f32 + 1.0);
+                let idx = map.xy_idx(tile.x, tile.y);
+                map.light[idx] = map.light[idx] + light.colour * intensity;
+            }
+        }
+        for light in map.light.iter_mut() {
+            *light = RGB::from_f32(light.r.min(1.0), light.g.min(1.0), light.b.min(1.0));
+        }
+
+        // In the dark, what the player can see changes whenever the lighting does
+        if map.dark && map.light != previous {
+            for (viewshed, _player) in (&mut viewsheds, &players).join() {
+                viewshed.dirty = true;
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 extern crate rltk;
-use rltk::{Console, GameState, Point, Rltk};
+use rltk::{Console, GameState, Point, Rltk, RGB};
 extern crate specs;
 use specs::prelude::*;
 #[macro_use]
@@ -20,13 +20,33 @@ mod map_indexing_system;
 use map_indexing_system::MapIndexingSystem;
 mod damage_system;
 use damage_system::DamageSystem;
+mod corpse_system;
+use corpse_system::CorpseDecaySystem;
+mod lighting_system;
+use lighting_system::LightingSystem;
+mod map_memory_system;
+use map_memory_system::MapMemorySystem;
+mod particle_system;
+use particle_system::{ParticleBuilder, ParticleSpawnSystem};
 mod melee_combat_system;
 use melee_combat_system::MeleeCombatSystem;
+mod faction;
+mod flow_map_system;
+use flow_map_system::{FlowMapSystem, FlowMaps};
 mod gamelog;
+mod identification;
 mod gui;
 mod inventory_system;
+mod noise_system;
+use noise_system::NoiseSystem;
 mod spawner;
-use inventory_system::{ItemCollectionSystem, ItemDropSystem, ItemUseSystem};
+mod status_system;
+mod uniques;
+use status_system::StatusSystem;
+use inventory_system::{
+    EncumbranceSystem, GoldCollectionSystem, ItemCollectionSystem, ItemDropSystem,
+    ItemThrowSystem, ItemUseSystem,
+};
 
 rltk::add_wasm_support!();
 
@@ -39,6 +59,26 @@ pub enum RunState {
     ShowInventory,
     ShowDropItem,
     ShowTargeting { range: i32, item: Entity },
+    ShowVendor { vendor: Entity, mode: gui::VendorMode },
+    ShowContainer { container: Entity },
+    ShowThrowItem,
+    ShowThrowTargeting { item: Entity },
+    MagicMapReveal { row: i32 },
+    ShowAllyCommand,
+    ShowAllyTargeting,
+    MainMenu { selection: usize },
+    PauseMenu { selection: usize },
+    Options { selection: usize, paused: bool },
+    GameOver,
+}
+
+/// Whether there's a game on screen, rather than a menu that takes the place of one.
+fn in_game(runstate: RunState) -> bool {
+    match runstate {
+        RunState::MainMenu { .. } => false,
+        RunState::Options { paused, .. } => paused,
+        _ => true,
+    }
 }
 
 pub struct State {
@@ -46,8 +86,90 @@ pub struct State {
 }
 
 impl State {
+    /// Draws the map, everything the player can see on it, and the UI.
+    fn render(&self, ctx: &mut Rltk) {
+        draw_map(&self.ecs, ctx);
+
+        let positions = self.ecs.read_storage::<Position>();
+        let renderables = self.ecs.read_storage::<Renderable>();
+        let map = self.ecs.fetch::<Map>();
+        let entities = self.ecs.entities();
+        let monsters = self.ecs.read_storage::<Monster>();
+        let items = self.ecs.read_storage::<Item>();
+        let statuses = self.ecs.read_storage::<StatusEffects>();
+        let player_status = statuses.get(*self.ecs.fetch::<Entity>());
+        let detects = |kind| player_status.is_some_and(|s| s.has(kind));
+        let detect_monsters = detects(StatusKind::DetectMonsters);
+        let detect_items = detects(StatusKind::DetectItems);
+
+        // Things seen earlier stay where the player last saw them, greyed out like the walls
+        for (idx, thing) in map.remembered.iter() {
+            if !map.visible_tiles[*idx] {
+                let x = *idx as i32 % map.width;
+                let y = *idx as i32 / map.width;
+                ctx.set(x, y, thing.fg.to_greyscale(), map.tile_background(*idx).to_greyscale(), thing.glyph);
+            }
+        }
+
+        let mut data = (&entities, &positions, &renderables).join().collect::<Vec<_>>();
+        data.sort_by_key(|&(_entity, _pos, render)| std::cmp::Reverse(render.render_order));
+        for (entity, pos, render) in data.iter() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            // Detection lets the player see some things through walls
+            let detected = (detect_monsters && monsters.get(*entity).is_some())
+                || (detect_items && items.get(*entity).is_some());
+            if map.visible_tiles[idx] || detected {
+                // Let bloodstains show through anything without a background of its own
+                let bg = if render.bg == RGB::named(rltk::BLACK) { map.tile_background(idx) } else { render.bg };
+                ctx.set(pos.x, pos.y, render.fg, bg, render.glyph)
+            }
+        }
+
+        gui::draw_ui(&self.ecs, ctx);
+    }
+
+    /// Throws away whatever game was going on, and sets up a fresh one.
+    fn new_game(&mut self) {
+        self.ecs.delete_all();
+
+        let rng = rltk::RandomNumberGenerator::new();
+        self.ecs.insert(identification::Identification::default());
+        self.ecs.insert(rng);
+        self.ecs.insert(FlowMaps::default());
+        self.ecs.insert(uniques::UniqueRegistry::default());
+        self.ecs.insert(ParticleBuilder::default());
+        self.ecs.insert(gamelog::GameStats::default());
+        self.ecs.insert(PlayerActions::default());
+        // Generate Map
+        let map: Map = Map::new_map_rooms_and_corridors(1);
+        // Get Player's Spawn point
+        let (player_x, player_y) = map.rooms[0].center();
+        // Initialize Player Entity
+        let player_entity = spawner::player(&mut self.ecs, player_x, player_y);
+        spawner::dog(&mut self.ecs, player_x + 1, player_y);
+        // Spawn Stuff in Rooms
+        for room in map.rooms.iter().skip(1) {
+            spawner::spawn_room(&mut self.ecs, room);
+        }
+        // Set up shop in the last room, away from the player
+        let (vendor_x, vendor_y) = map.rooms[map.rooms.len() - 1].center();
+        spawner::vendor(&mut self.ecs, vendor_x, vendor_y);
+        spawner::spawn_uniques(&mut self.ecs, &map);
+
+        self.ecs.insert(map);
+        // Register Player's Point with the world
+        self.ecs.insert(Point::new(player_x, player_y));
+        self.ecs.insert(player_entity);
+        self.ecs.insert(gamelog::GameLog {
+            entries: vec!["Welcome to Rustlike".to_string()],
+        });
+    }
+
     fn run_systems(&mut self) {
+        let mut lighting = LightingSystem {};
         let mut vis = VisibilitySystem {};
+        let mut noise = NoiseSystem {};
+        let mut flow = FlowMapSystem {};
         let mut mob = MonsterAI {};
         let mut map_idx = MapIndexingSystem {};
         let mut dmg = DamageSystem {};
@@ -55,14 +177,31 @@ impl State {
         let mut pickup = ItemCollectionSystem {};
         let mut potions = ItemUseSystem {};
         let mut drop_items = ItemDropSystem {};
+        let mut status = StatusSystem {};
+        let mut encumbrance = EncumbranceSystem {};
+        let mut gold = GoldCollectionSystem {};
+        let mut throw_items = ItemThrowSystem {};
+        let mut corpses = CorpseDecaySystem {};
+        let mut memory = MapMemorySystem {};
+        let mut particles = ParticleSpawnSystem {};
+        lighting.run_now(&self.ecs);
         vis.run_now(&self.ecs);
+        noise.run_now(&self.ecs);
+        flow.run_now(&self.ecs);
         mob.run_now(&self.ecs);
+        status.run_now(&self.ecs);
         map_idx.run_now(&self.ecs);
+        gold.run_now(&self.ecs);
         melee.run_now(&self.ecs);
         dmg.run_now(&self.ecs);
         pickup.run_now(&self.ecs);
         drop_items.run_now(&self.ecs);
+        throw_items.run_now(&self.ecs);
+        encumbrance.run_now(&self.ecs);
         potions.run_now(&self.ecs);
+        corpses.run_now(&self.ecs);
+        memory.run_now(&self.ecs);
+        particles.run_now(&self.ecs);
         self.ecs.maintain();
     }
 }
@@ -70,6 +209,7 @@ impl State {
 impl GameState for State {
     fn tick(&mut self, ctx: &mut Rltk) {
         ctx.cls();
+        particle_system::cull_dead_particles(&mut self.ecs, ctx.frame_time_ms);
         let mut newrunstate;
         {
             // Borrow-Checker Scope
@@ -77,6 +217,11 @@ impl GameState for State {
             newrunstate = *runstate;
         }
 
+        // Menus and the like are drawn on top of the game
+        if in_game(newrunstate) {
+            self.render(ctx);
+        }
+
         match newrunstate {
             RunState::PreRun => {
                 self.run_systems();
@@ -89,12 +234,28 @@ impl GameState for State {
             RunState::PlayerTurn => {
                 self.run_systems();
                 self.ecs.maintain();
-                newrunstate = RunState::MonsterTurn;
+                let item_runstate = *self.ecs.fetch::<RunState>();
+                if let RunState::MagicMapReveal { .. } = item_runstate {
+                    // An item wants to play out its effect before anyone else moves
+                    newrunstate = item_runstate;
+                } else if player_has_bonus_action(&self.ecs) {
+                    newrunstate = RunState::AwaitingInput;
+                } else {
+                    newrunstate = RunState::MonsterTurn;
+                }
             }
             RunState::MonsterTurn => {
                 self.run_systems();
                 self.ecs.maintain();
-                newrunstate = RunState::AwaitingInput;
+                self.ecs.write_resource::<gamelog::GameStats>().turns += 1;
+                self.ecs.write_resource::<PlayerActions>().taken = 0;
+                if player_can_act(&self.ecs) {
+                    newrunstate = RunState::AwaitingInput;
+                } else {
+                    // The player loses this turn, but it still passes
+                    player_lose_turn(&mut self.ecs);
+                    newrunstate = RunState::PlayerTurn;
+                }
             }
             RunState::ShowInventory => {
                 let result = gui::show_inventory(self, ctx);
@@ -156,6 +317,171 @@ impl GameState for State {
                     }
                 }
             }
+            RunState::MagicMapReveal { row } => {
+                let mut map = self.ecs.fetch_mut::<Map>();
+                for x in 0..map.width {
+                    let idx = map.xy_idx(x, row);
+                    map.revealed_tiles[idx] = true;
+                }
+                if row == map.height - 1 {
+                    newrunstate = RunState::MonsterTurn;
+                } else {
+                    newrunstate = RunState::MagicMapReveal { row: row + 1 };
+                }
+            }
+            RunState::ShowThrowItem => {
+                let result = gui::throw_item_menu(self, ctx);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        newrunstate = RunState::ShowThrowTargeting {
+                            item: result.1.unwrap(),
+                        };
+                    }
+                }
+            }
+            RunState::ShowThrowTargeting { item } => {
+                let result = gui::ranged_target(self, ctx, inventory_system::THROW_RANGE);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        let mut intent = self.ecs.write_storage::<WantsToThrowItem>();
+                        intent
+                            .insert(
+                                *self.ecs.fetch::<Entity>(),
+                                WantsToThrowItem {
+                                    item,
+                                    target: result.1.unwrap(),
+                                },
+                            )
+                            .expect("Unable to insert intent");
+                        newrunstate = RunState::PlayerTurn;
+                    }
+                }
+            }
+            RunState::ShowAllyCommand => {
+                let result = gui::ally_command_menu(ctx);
+                match result {
+                    gui::AllyMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::AllyMenuResult::NoResponse => {}
+                    gui::AllyMenuResult::Follow => {
+                        player::command_allies(&mut self.ecs, AllyCommand::Follow);
+                        newrunstate = RunState::AwaitingInput;
+                    }
+                    gui::AllyMenuResult::Stay => {
+                        player::command_allies(&mut self.ecs, AllyCommand::Stay);
+                        newrunstate = RunState::AwaitingInput;
+                    }
+                    gui::AllyMenuResult::Attack => newrunstate = RunState::ShowAllyTargeting,
+                }
+            }
+            RunState::ShowAllyTargeting => {
+                let result = gui::ranged_target(self, ctx, player::COMMAND_RANGE);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        if player::command_allies_attack(&mut self.ecs, result.1.unwrap()) {
+                            newrunstate = RunState::AwaitingInput;
+                        }
+                    }
+                }
+            }
+            RunState::MainMenu { selection } => {
+                // There's only something to go back to if the player is still alive
+                let can_continue = self.ecs.has_value::<gamelog::GameStats>()
+                    && self.ecs.fetch::<gamelog::GameStats>().cause_of_death.is_none();
+                match gui::main_menu(&self.ecs, ctx, selection, can_continue) {
+                    gui::MenuResult::NoResponse { selection } => newrunstate = RunState::MainMenu { selection },
+                    gui::MenuResult::Cancel => {}
+                    gui::MenuResult::Selected(gui::MainMenuSelection::NewGame) => {
+                        self.new_game();
+                        newrunstate = RunState::PreRun;
+                    }
+                    gui::MenuResult::Selected(gui::MainMenuSelection::Continue) => newrunstate = RunState::AwaitingInput,
+                    gui::MenuResult::Selected(gui::MainMenuSelection::Options) => {
+                        newrunstate = RunState::Options { selection: 0, paused: false };
+                    }
+                    gui::MenuResult::Selected(gui::MainMenuSelection::Quit) => ctx.quit(),
+                }
+            }
+            RunState::PauseMenu { selection } => {
+                match gui::pause_menu(&self.ecs, ctx, selection) {
+                    gui::MenuResult::NoResponse { selection } => newrunstate = RunState::PauseMenu { selection },
+                    gui::MenuResult::Cancel | gui::MenuResult::Selected(gui::PauseMenuSelection::Resume) => {
+                        newrunstate = RunState::AwaitingInput;
+                    }
+                    gui::MenuResult::Selected(gui::PauseMenuSelection::Options) => {
+                        newrunstate = RunState::Options { selection: 0, paused: true };
+                    }
+                    gui::MenuResult::Selected(gui::PauseMenuSelection::MainMenu) => newrunstate = RunState::MainMenu { selection: 0 },
+                    gui::MenuResult::Selected(gui::PauseMenuSelection::Quit) => ctx.quit(),
+                }
+            }
+            RunState::GameOver => {
+                match gui::game_over(&self.ecs, ctx) {
+                    gui::GameOverResult::NoResponse => {}
+                    gui::GameOverResult::NewGame => {
+                        self.new_game();
+                        newrunstate = RunState::PreRun;
+                    }
+                    gui::GameOverResult::MainMenu => newrunstate = RunState::MainMenu { selection: 0 },
+                }
+            }
+            RunState::Options { selection, paused } => {
+                let result = gui::options_menu(&self.ecs, ctx, selection);
+                let mut options = self.ecs.write_resource::<gui::Options>();
+                match result {
+                    gui::MenuResult::NoResponse { selection } => newrunstate = RunState::Options { selection, paused },
+                    gui::MenuResult::Selected(gui::OptionsSelection::Particles) => options.particles = !options.particles,
+                    gui::MenuResult::Selected(gui::OptionsSelection::Scanlines) => {
+                        options.scanlines = !options.scanlines;
+                        ctx.post_scanlines = options.scanlines;
+                    }
+                    gui::MenuResult::Cancel | gui::MenuResult::Selected(gui::OptionsSelection::Back) => {
+                        newrunstate = if paused {
+                            RunState::PauseMenu { selection: 1 }
+                        } else {
+                            RunState::MainMenu { selection: 0 }
+                        };
+                    }
+                }
+            }
+            RunState::ShowContainer { container } => {
+                let result = gui::show_container(self, ctx, container);
+                match result.0 {
+                    gui::ItemMenuResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::ItemMenuResult::NoResponse => {}
+                    gui::ItemMenuResult::Selected => {
+                        inventory_system::take_from_container(&mut self.ecs, result.1.unwrap());
+                    }
+                }
+            }
+            RunState::ShowVendor { vendor, mode } => {
+                let result = gui::show_vendor(self, ctx, vendor, mode);
+                match result.0 {
+                    gui::VendorResult::Cancel => newrunstate = RunState::AwaitingInput,
+                    gui::VendorResult::NoResponse => {}
+                    gui::VendorResult::Switch => {
+                        let mode = match mode {
+                            gui::VendorMode::Buy => gui::VendorMode::Sell,
+                            gui::VendorMode::Sell => gui::VendorMode::Buy,
+                        };
+                        newrunstate = RunState::ShowVendor { vendor, mode };
+                    }
+                    gui::VendorResult::Selected => {
+                        let item_entity = result.1.unwrap();
+                        match mode {
+                            gui::VendorMode::Buy => inventory_system::buy_item(&mut self.ecs, item_entity),
+                            gui::VendorMode::Sell => {
+                                inventory_system::sell_item(&mut self.ecs, vendor, item_entity)
+                            }
+                        }
+                    }
+                }
+            }
         }
 
         {
@@ -164,32 +490,15 @@ impl GameState for State {
             *runwriter = newrunstate;
         }
         // *BONG* BRING OUT YER DEAD *BONG*
-        damage_system::delete_the_dead(&mut self.ecs);
-        // Render Loop
-        {
-            draw_map(&self.ecs, ctx);
-
-            let positions = self.ecs.read_storage::<Position>();
-            let renderables = self.ecs.read_storage::<Renderable>();
-            let map = self.ecs.fetch::<Map>();
-
-            let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
-            data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order));
-            for (pos, render) in data.iter() {
-                let idx = map.xy_idx(pos.x, pos.y);
-                if map.visible_tiles[idx] {
-                    ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph)
-                }
-            }
+        if in_game(newrunstate) {
+            damage_system::delete_the_dead(&mut self.ecs);
         }
-
-        gui::draw_ui(&self.ecs, ctx);
     }
 }
 
 fn main() {
     let mut context = Rltk::init_simple8x8(80, 50, "Rustlike", "resources");
-    context.with_post_scanlines(true);
+    context.with_post_scanlines(gui::Options::default().scanlines);
     let mut gs = State { ecs: World::new() };
 
     // Register Components to World
@@ -198,6 +507,23 @@ fn main() {
     gs.ecs.register::<Player>();
     gs.ecs.register::<Viewshed>();
     gs.ecs.register::<Monster>();
+    gs.ecs.register::<Brain>();
+    gs.ecs.register::<Faction>();
+    gs.ecs.register::<Caster>();
+    gs.ecs.register::<Spell>();
+    gs.ecs.register::<Hearing>();
+    gs.ecs.register::<Stealth>();
+    gs.ecs.register::<MakesNoise>();
+    gs.ecs.register::<Unique>();
+    gs.ecs.register::<Scavenger>();
+    gs.ecs.register::<LootTable>();
+    gs.ecs.register::<Corpse>();
+    gs.ecs.register::<ParticleLifetime>();
+    gs.ecs.register::<LightSource>();
+    gs.ecs.register::<LastHitBy>();
+    gs.ecs.register::<Ally>();
+    gs.ecs.register::<Hireable>();
+    gs.ecs.register::<Charms>();
     gs.ecs.register::<Name>();
     gs.ecs.register::<BlocksTile>();
     gs.ecs.register::<CombatStats>();
@@ -206,7 +532,8 @@ fn main() {
     gs.ecs.register::<Item>();
     gs.ecs.register::<Consumable>();
     gs.ecs.register::<Ranged>();
-    gs.ecs.register::<Confusion>();
+    gs.ecs.register::<InflictsStatus>();
+    gs.ecs.register::<StatusEffects>();
     gs.ecs.register::<InBackpack>();
     gs.ecs.register::<AreaOfEffect>();
     gs.ecs.register::<ProvidesHealing>();
@@ -214,28 +541,29 @@ fn main() {
     gs.ecs.register::<WantsToPickupItem>();
     gs.ecs.register::<WantsToUseItem>();
     gs.ecs.register::<WantsToDropItem>();
+    gs.ecs.register::<IdentifiesItems>();
+    gs.ecs.register::<Charges>();
+    gs.ecs.register::<Weight>();
+    gs.ecs.register::<Burdened>();
+    gs.ecs.register::<Wallet>();
+    gs.ecs.register::<Gold>();
+    gs.ecs.register::<Value>();
+    gs.ecs.register::<Vendor>();
+    gs.ecs.register::<Container>();
+    gs.ecs.register::<InContainer>();
+    gs.ecs.register::<Locked>();
+    gs.ecs.register::<Trapped>();
+    gs.ecs.register::<Fragile>();
+    gs.ecs.register::<WantsToThrowItem>();
+    gs.ecs.register::<MagicMapper>();
+    gs.ecs.register::<Teleports>();
+    gs.ecs.register::<Buc>();
+    gs.ecs.register::<RemovesCurse>();
 
-
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-    // Generate Map
-    let map: Map = Map::new_map_rooms_and_corridors();
-    // Get Player's Spawn point
-    let (player_x, player_y) = map.rooms[0].center();
-    // Initialize Player Entity
-    let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
-    // Spawn Stuff in Rooms
-    for room in map.rooms.iter().skip(1) {
-        spawner::spawn_room(&mut gs.ecs, room);
-    }
-
-    gs.ecs.insert(map);
-    // Register Player's Point with the world
-    gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(player_entity);
-    gs.ecs.insert(RunState::PreRun);
-    gs.ecs.insert(gamelog::GameLog {
-        entries: vec!["Welcome to Rustlike".to_string()],
-    });
+    // The game itself is set up once the player picks New Game
+    gs.ecs.insert(gui::Options::default());
+    gs.ecs.insert(gui::MenuMouse::default());
+    gs.ecs.insert(RunState::MainMenu { selection: 0 });
 
     rltk::main_loop(context, gs);
 }
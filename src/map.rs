@@ -2,6 +2,7 @@ extern crate rltk;
 use super::Rect;
 use rltk::{Algorithm2D, BaseMap, Console, Point, RandomNumberGenerator, Rltk, RGB};
 use std::cmp::{max, min};
+use std::collections::{HashMap, HashSet};
 extern crate specs;
 use specs::prelude::*;
 
@@ -9,12 +10,23 @@ pub const MAPWIDTH: usize = 80;
 pub const MAPHEIGHT: usize = 43;
 pub const MAPCOUNT: usize = MAPHEIGHT * MAPWIDTH;
 
+/// Tiles any darker than this can't be made out.
+const MIN_LIGHT: f32 = 0.1;
+
 #[derive(PartialEq, Copy, Clone)]
 pub enum TileType {
     Wall,
     Floor,
 }
 
+/// The last thing the player saw lying on a tile, drawn there while it's out of sight.
+#[derive(Clone)]
+pub struct RememberedEntity {
+    pub glyph: u8,
+    pub fg: RGB,
+    pub name: String,
+}
+
 #[derive(Default)]
 pub struct Map {
     pub tiles: Vec<TileType>,
@@ -25,6 +37,11 @@ pub struct Map {
     pub visible_tiles: Vec<bool>,
     pub blocked: Vec<bool>,
     pub tile_content: Vec<Vec<Entity>>,
+    pub depth: i32,
+    pub bloodstains: HashSet<usize>,
+    pub light: Vec<RGB>,
+    pub dark: bool,
+    pub remembered: HashMap<usize, RememberedEntity>,
 }
 
 impl Map {
@@ -32,6 +49,21 @@ impl Map {
         (y as usize * self.width as usize) + x as usize
     }
 
+    /// Whether there is enough light on a tile to see by.
+    pub fn is_lit(&self, idx: usize) -> bool {
+        let light = self.light[idx];
+        light.r.max(light.g).max(light.b) > MIN_LIGHT
+    }
+
+    /// The background colour of a tile: black, unless someone has bled on it.
+    pub fn tile_background(&self, idx: usize) -> RGB {
+        if self.bloodstains.contains(&idx) {
+            RGB::from_f32(0.4, 0., 0.)
+        } else {
+            RGB::from_f32(0., 0., 0.)
+        }
+    }
+
     fn apply_room_to_map(&mut self, room: &Rect) {
         for y in room.y1 + 1..=room.y2 {
             for x in room.x1 + 1..=room.x2 {
@@ -46,7 +78,7 @@ impl Map {
         for x in min(x1, x2)..=max(x1, x2) {
             let idx = self.xy_idx(x, y);
             if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
+                self.tiles[idx] = TileType::Floor;
             }
         }
     }
@@ -56,13 +88,13 @@ impl Map {
         for y in min(y1, y2)..=max(y1, y2) {
             let idx = self.xy_idx(x, y);
             if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
+                self.tiles[idx] = TileType::Floor;
             }
         }
     }
     /// Makes a new map using the algorithm from http://rogueliketutorials.com/tutorials/tcod/part-3/
     /// This gives a handful of random rooms and corridors joining them together.
-    pub fn new_map_rooms_and_corridors() -> Map {
+    pub fn new_map_rooms_and_corridors(depth: i32) -> Map {
         let mut map = Map {
             tiles: vec![TileType::Wall; MAPCOUNT],
             rooms: Vec::new(),
@@ -72,6 +104,11 @@ impl Map {
             visible_tiles: vec![false; MAPCOUNT],
             blocked: vec![false; MAPCOUNT],
             tile_content: vec![Vec::new(); MAPCOUNT],
+            depth,
+            bloodstains: HashSet::new(),
+            light: vec![RGB::from_f32(0., 0., 0.); MAPCOUNT],
+            dark: false,
+            remembered: HashMap::new(),
         };
 
         const MAX_ROOMS: i32 = 30;
@@ -109,6 +146,9 @@ impl Map {
             }
         }
 
+        // The deeper you go, the more likely it is that nobody has lit the place
+        map.dark = rng.roll_dice(1, 4) <= min(depth, 3);
+
         map
     }
 
@@ -220,10 +260,14 @@ pub fn draw_map(ecs: &World, ctx: &mut Rltk) {
                     fg = RGB::from_f32(0., 1.0, 0.);
                 }
             }
-            if !map.visible_tiles[idx] {
-                fg = fg.to_greyscale()
+            let mut bg = map.tile_background(idx);
+            if map.visible_tiles[idx] {
+                fg = fg * map.light[idx];
+            } else {
+                fg = fg.to_greyscale();
+                bg = bg.to_greyscale();
             }
-            ctx.set(x, y, fg, RGB::from_f32(0., 0., 0.), glyph);
+            ctx.set(x, y, fg, bg, glyph);
         }
 
         // Move the coordinates
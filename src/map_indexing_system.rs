@@ -1,5 +1,5 @@
 extern crate specs;
-use super::{BlocksTile, Map, Position};
+use super::{BlocksTile, Map, ParticleLifetime, Position};
 use specs::prelude::*;
 
 pub struct MapIndexingSystem {}
@@ -9,15 +9,17 @@ impl<'a> System<'a> for MapIndexingSystem {
         WriteExpect<'a, Map>,
         ReadStorage<'a, Position>,
         ReadStorage<'a, BlocksTile>,
+        ReadStorage<'a, ParticleLifetime>,
         Entities<'a>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, position, blockers, entities) = data;
+        let (mut map, position, blockers, particles, entities) = data;
 
         map.populate_blocked();
         map.clear_content_index();
-        for (entity, position) in (&entities, &position).join() {
+        // Particles are just for show, so nothing should ever find them on the map
+        for (entity, position, _particle) in (&entities, &position, !&particles).join() {
             let idx = map.xy_idx(position.x, position.y);
 
             // If they block, update the blocking list
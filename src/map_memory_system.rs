@@ -0,0 +1,59 @@
+extern crate specs;
+use super::{
+    map::RememberedEntity, Map, Monster, Name, ParticleLifetime, Player, Position, Renderable,
+};
+use specs::prelude::*;
+
+pub struct MapMemorySystem {}
+
+impl<'a> System<'a> for MapMemorySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        WriteExpect<'a, Map>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Renderable>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Monster>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, ParticleLifetime>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (mut map, positions, renderables, names, monsters, players, particles) = data;
+
+        // Forget whatever used to be on the tiles in view, then remember what's there now
+        let mut seen: Vec<(usize, i32, RememberedEntity)> = Vec::new();
+        for (pos, render, name, _monster, _player, _particle) in (
+            &positions,
+            &renderables,
+            &names,
+            !&monsters,
+            !&players,
+            !&particles,
+        )
+            .join()
+        {
+            let idx = map.xy_idx(pos.x, pos.y);
+            if !map.visible_tiles[idx] {
+                continue;
+            }
+            seen.push((
+                idx,
+                render.render_order,
+                RememberedEntity {
+                    glyph: render.glyph,
+                    fg: render.fg,
+                    name: name.name.clone(),
+                },
+            ));
+        }
+
+        let visible = map.visible_tiles.clone();
+        map.remembered.retain(|idx, _thing| !visible[*idx]);
+        // Only the thing drawn on top is remembered, just as it was drawn
+        seen.sort_by_key(|s| std::cmp::Reverse(s.1));
+        for (idx, _order, thing) in seen {
+            map.remembered.insert(idx, thing);
+        }
+    }
+}
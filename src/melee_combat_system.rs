@@ -1,7 +1,15 @@
 extern crate specs;
-use super::{gamelog::GameLog, CombatStats, Name, SufferDamage, WantsToMelee};
+use super::{
+    gamelog::GameLog,
+    noise_system::{make_noise, COMBAT},
+    particle_system::ParticleBuilder,
+    Burdened, CombatStats, MakesNoise, Map, Name, Position, SufferDamage, WantsToMelee,
+};
+use rltk::RGB;
 use specs::prelude::*;
 
+const BURDEN_PENALTY: i32 = 2;
+
 pub struct MeleeCombatSystem {}
 
 impl<'a> System<'a> for MeleeCombatSystem {
@@ -12,12 +20,36 @@ impl<'a> System<'a> for MeleeCombatSystem {
         ReadStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
         WriteExpect<'a, GameLog>,
+        ReadStorage<'a, Burdened>,
+        ReadExpect<'a, Map>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, MakesNoise>,
+        WriteExpect<'a, ParticleBuilder>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut wants_melee, names, combat_stats, mut inflict_damage, mut log) = data;
+        let (
+            entities,
+            mut wants_melee,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            mut log,
+            burdened,
+            map,
+            positions,
+            mut noises,
+            mut particles,
+        ) = data;
+
+        // Monsters fight each other all over the dungeon; only report the fights the player can see
+        let seen = |entity: Entity| {
+            positions
+                .get(entity)
+                .is_some_and(|pos| map.visible_tiles[map.xy_idx(pos.x, pos.y)])
+        };
 
-        for (_entity, wants_melee, name, stats) in
+        for (entity, wants_melee, name, stats) in
             (&entities, &wants_melee, &names, &combat_stats).join()
         {
             if stats.hp > 0 {
@@ -25,24 +57,51 @@ impl<'a> System<'a> for MeleeCombatSystem {
                 if target_stats.hp > 0 {
                     let target_name = names.get(wants_melee.target).unwrap();
 
-                    let damage = i32::max(0, stats.power - target_stats.defense);
+                    // Lugging too much around makes for clumsy fighting, both ways
+                    let mut power = stats.power;
+                    if burdened.get(entity).is_some() {
+                        power -= BURDEN_PENALTY;
+                    }
+                    let mut defense = target_stats.defense;
+                    if burdened.get(wants_melee.target).is_some() {
+                        defense -= BURDEN_PENALTY;
+                    }
+                    let damage = i32::max(0, power - defense);
+                    let noticed = seen(entity) || seen(wants_melee.target);
+                    make_noise(&mut noises, entity, COMBAT);
 
                     if damage == 0 {
-                        log.entries.insert(
-                            0,
-                            format!("{} is unable to hurt {}", &name.name, &target_name.name),
-                        );
+                        if noticed {
+                            log.entries.insert(
+                                0,
+                                format!("{} is unable to hurt {}", &name.name, &target_name.name),
+                            );
+                        }
                     } else {
-                        log.entries.insert(
-                            0,
-                            format!(
-                                "{} hits {}, for {} hp.",
-                                &name.name, &target_name.name, damage
-                            ),
+                        if noticed {
+                            log.entries.insert(
+                                0,
+                                format!(
+                                    "{} hits {}, for {} hp.",
+                                    &name.name, &target_name.name, damage
+                                ),
+                            );
+                        }
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            wants_melee.target,
+                            damage,
+                            entity,
                         );
-                        inflict_damage
-                            .insert(wants_melee.target, SufferDamage { amount: damage })
-                            .expect("Unable to do damage");
+                        if let Some(pos) = positions.get(wants_melee.target) {
+                            particles.request(
+                                pos.x,
+                                pos.y,
+                                RGB::named(rltk::ORANGE),
+                                rltk::to_cp437('‼'),
+                                200.0,
+                            );
+                        }
                     }
                 }
             }
@@ -1,64 +1,332 @@
 extern crate specs;
 use specs::prelude::*;
-use super::{Viewshed, Monster, Map, Position, WantsToMelee, RunState, Confusion};
+use super::{Viewshed, Monster, Map, Position, WantsToMelee, RunState, StatusEffects, StatusKind,
+            AiState, Brain, CombatStats, Faction, Name, gamelog::GameLog, faction::{reaction, Reaction},
+            Caster, InBackpack, Ranged, WantsToUseItem, flow_map_system::FlowMaps, Ally, AllyCommand,
+            Scavenger, Item, ProvidesHealing, InflictsDamage, InflictsStatus, Charges, AreaOfEffect,
+            WantsToPickupItem};
 extern crate rltk;
-use rltk::{Point};
+use rltk::{Point, RandomNumberGenerator};
+
+/// Set to log every AI state change to the game log.
+pub const DEBUG_AI: bool = false;
+/// How many turns a monster keeps looking for a target it has lost sight of.
+pub const SEARCH_PATIENCE: i32 = 10;
+/// Chance (1 in N) each turn that an idle monster starts wandering, or a wandering one stops.
+const WANDER_CHANCE: i32 = 8;
+/// Chance (1 in N) each turn that a sleeping monster notices a player it can see.
+const WAKE_CHANCE: i32 = 4;
+/// Chance (1 in N) each turn that a monster with a spell or item in range uses it.
+const CAST_CHANCE: i32 = 2;
+/// How far (in steps) an idle scavenger will go out of its way for an item.
+const SCAVENGE_RANGE: f32 = 6.0;
+/// How close a following ally tries to stay to the player.
+const FOLLOW_DISTANCE: f32 = 2.5;
 
 pub struct MonsterAI {}
 
 impl<'a> System<'a> for MonsterAI {
     #[allow(clippy::type_complexity)]
     type SystemData = ( WriteExpect<'a, Map>,
-                        ReadExpect<'a, Point>,
-                        ReadExpect<'a, Entity>,
                         ReadExpect<'a, RunState>,
+                        ReadExpect<'a, FlowMaps>,
+                        WriteExpect<'a, RandomNumberGenerator>,
+                        WriteExpect<'a, GameLog>,
                         Entities<'a>,
                         WriteStorage<'a, Viewshed>,
                         ReadStorage<'a, Monster>,
                         WriteStorage<'a, Position>,
                         WriteStorage<'a, WantsToMelee>,
-                        WriteStorage<'a, Confusion>);
+                        ReadStorage<'a, StatusEffects>,
+                        WriteStorage<'a, Brain>,
+                        ReadStorage<'a, CombatStats>,
+                        ReadStorage<'a, Name>,
+                        ReadStorage<'a, Faction>,
+                        ReadStorage<'a, Caster>,
+                        ReadStorage<'a, InBackpack>,
+                        WriteStorage<'a, WantsToUseItem>,
+                        ReadExpect<'a, Point>,
+                        WriteStorage<'a, Ally>,
+                        ReadStorage<'a, Scavenger>,
+                        WriteStorage<'a, WantsToPickupItem>,
+                        ( ReadStorage<'a, Item>,
+                          ReadStorage<'a, ProvidesHealing>,
+                          ReadStorage<'a, Ranged>,
+                          ReadStorage<'a, InflictsDamage>,
+                          ReadStorage<'a, InflictsStatus>,
+                          ReadStorage<'a, Charges>,
+                          ReadStorage<'a, AreaOfEffect> ));
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut map, player_pos, player_entity, runstate, entities, mut viewshed, monster, mut position, mut wants_to_melee, mut confused) = data;
+        let (mut map, runstate, flow_maps, mut rng, mut gamelog, entities, mut viewshed, monster,
+             mut position, mut wants_to_melee, statuses, mut brains, combat_stats, names, factions,
+             casters, backpack, mut wants_to_use, player_pos, mut allies, scavengers, mut wants_pickup,
+             (items, healing, ranged, inflicts_damage, inflicts_status, charges, aoe)) = data;
 
         if *runstate != RunState::MonsterTurn { return; }
 
-        for (entity, mut viewshed,_monster, mut pos) in (&entities, &mut viewshed, &monster, &mut position).join() {
-            let mut can_act = true;
+        // Everyone who could be a target, and where they are. Kept up to date as monsters move.
+        let mut creatures : Vec<(Entity, Point, String)> = (&entities, &position, &factions, &combat_stats).join()
+            .map(|(e, pos, faction, _stats)| (e, Point::new(pos.x, pos.y), faction.name.clone()))
+            .collect();
+        // The player doesn't block their own tile, but nobody should walk onto them either
+        for creature in creatures.iter() {
+            let idx = map.xy_idx(creature.1.x, creature.1.y);
+            map.blocked[idx] = true;
+        }
+
+        for (entity, viewshed,_monster, pos, brain) in (&entities, &mut viewshed, &monster, &mut position, &mut brains).join() {
+            let mut actions = 1;
+            let mut confused = false;
+            if let Some(status) = statuses.get(entity) {
+                actions = status.actions();
+                confused = status.has(StatusKind::Confusion);
+            }
+
+            // Confused monsters stumble around at random, just like the player does
+            if confused {
+                if actions > 0 {
+                    let x = pos.x + rng.range(-1, 2);
+                    let y = pos.y + rng.range(-1, 2);
+                    try_step(&mut map, pos, viewshed, x, y);
+                    update_position(&mut creatures, entity, pos);
+                }
+                continue;
+            }
+
+            // Find the nearest visible creature worth running from, or failing that, attacking
+            let my_faction = factions.get(entity).map_or("", |f| &f.name);
+            let here = Point::new(pos.x, pos.y);
+            let mut threat : Option<(Entity, Point, f32)> = None;
+            let mut prey : Option<(Entity, Point, f32)> = None;
+            for (other, other_pos, other_faction) in creatures.iter() {
+                if *other == entity || !viewshed.visible_tiles.contains(other_pos) { continue; }
+                let d = rltk::DistanceAlg::Pythagoras.distance2d(here, *other_pos);
+                let nearest = match reaction(my_faction, other_faction) {
+                    Reaction::Flee => &mut threat,
+                    Reaction::Attack => &mut prey,
+                    Reaction::Ignore => continue,
+                };
+                if nearest.is_none_or(|n| d < n.2) {
+                    *nearest = Some((*other, *other_pos, d));
+                }
+            }
+            let spotted = threat.or(prey);
+
+            let old_state = brain.state;
+            let can_see_target = spotted.is_some();
+            let hurt = combat_stats.get(entity).is_some_and(|s| s.hp < s.max_hp);
+            let badly_hurt = combat_stats.get(entity).is_some_and(|s| s.hp * 4 < s.max_hp);
 
-            let is_confused = confused.get_mut(entity);
-            if let Some(i_am_confused) = is_confused {
-                i_am_confused.turns -= 1;
-                if i_am_confused.turns < 1 {
-                    confused.remove(entity);
+            // Decide what to do this turn
+            if let Some((target, target_pos, _d)) = spotted {
+                brain.target = Some(target);
+                brain.last_seen = Some(target_pos);
+                brain.patience = SEARCH_PATIENCE;
+            }
+            brain.state = match brain.state {
+                AiState::Sleeping => {
+                    if hurt || (can_see_target && rng.roll_dice(1, WAKE_CHANCE) == 1) { AiState::Hunting } else { AiState::Sleeping }
                 }
-                can_act = false;
+                _ if threat.is_some() => AiState::Fleeing,
+                AiState::Idle | AiState::Wandering if can_see_target => AiState::Hunting,
+                AiState::Idle => {
+                    if rng.roll_dice(1, WANDER_CHANCE) == 1 { AiState::Wandering } else { AiState::Idle }
+                }
+                AiState::Wandering => {
+                    if rng.roll_dice(1, WANDER_CHANCE) == 1 { AiState::Idle } else { AiState::Wandering }
+                }
+                AiState::Hunting | AiState::Searching if badly_hurt => AiState::Fleeing,
+                AiState::Hunting | AiState::Searching if can_see_target => AiState::Hunting,
+                AiState::Hunting => AiState::Searching,
+                AiState::Searching | AiState::Fleeing => {
+                    if can_see_target || brain.patience > 0 {
+                        brain.patience -= 1;
+                        brain.state
+                    } else {
+                        brain.target = None;
+                        brain.last_seen = None;
+                        AiState::Wandering
+                    }
+                }
+            };
+            // Allies told to attack something go after it in particular, until it's dead
+            let mut command = allies.get(entity).map(|a| a.command);
+            if let Some(AllyCommand::Attack(victim)) = command {
+                match creatures.iter().find(|c| c.0 == victim) {
+                    Some(c) => {
+                        brain.state = AiState::Hunting;
+                        brain.target = Some(victim);
+                        brain.last_seen = Some(c.1);
+                        brain.patience = SEARCH_PATIENCE;
+                    }
+                    None => {
+                        allies.get_mut(entity).unwrap().command = AllyCommand::Follow;
+                        command = Some(AllyCommand::Follow);
+                    }
+                }
+            }
+            let commanded = matches!(command, Some(AllyCommand::Attack(_)));
+
+            if brain.state == AiState::Searching && brain.last_seen == Some(Point::new(pos.x, pos.y)) {
+                // Reached the last place the player was seen and they're not here
+                brain.patience -= 1;
+            }
+
+            if DEBUG_AI && brain.state != old_state {
+                let name = names.get(entity).map_or("Something", |n| &n.name);
+                gamelog.entries.insert(0, format!("[AI] {}: {:?} -> {:?}", name, old_state, brain.state));
             }
 
-            if can_act {
-                let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
-                if distance < 1.5 {
-                    wants_to_melee.insert(entity, WantsToMelee{ target: *player_entity }).expect("Unable to insert attack");
+            for _action in 0..actions {
+                // How far away the target really is, wherever they've got to
+                let target = brain.target.and_then(|t| creatures.iter().find(|c| c.0 == t)).map(|c| (c.0, c.1));
+                let distance = target.map_or(f32::MAX, |t| rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), t.1));
+
+                // Allies told to stay put only fight what comes to them
+                if command == Some(AllyCommand::Stay) {
+                    if distance < 1.5 && (brain.state == AiState::Hunting || brain.state == AiState::Searching) {
+                        wants_to_melee.insert(entity, WantsToMelee{ target: target.unwrap().0 }).expect("Unable to insert attack");
+                    }
+                    break;
                 }
-                else if viewshed.visible_tiles.contains(&*player_pos) {
-                    // Path to the player
-                    let path = rltk::a_star_search(
-                        map.xy_idx(pos.x, pos.y) as i32,
-                        map.xy_idx(player_pos.x, player_pos.y) as i32,
-                        &mut *map
-                    );
-                    if path.success && path.steps.len()>1 {
-                        let mut idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = false;
-                        pos.x = path.steps[1] % map.width;
-                        pos.y = path.steps[1] / map.width;
-                        idx = map.xy_idx(pos.x, pos.y);
-                        map.blocked[idx] = true;
-                        viewshed.dirty = true;
+
+                // Anyone badly hurt drinks a healing potion if they're carrying one
+                if combat_stats.get(entity).is_some_and(|s| s.hp * 2 < s.max_hp) && brain.state != AiState::Sleeping {
+                    let potion = (&entities, &backpack, &healing).join()
+                        .find(|(_e, pack, _heal)| pack.owner == entity)
+                        .map(|(e, _pack, _heal)| e);
+                    if let Some(potion) = potion {
+                        wants_to_use.insert(entity, WantsToUseItem{ item: potion, target: None }).expect("Unable to insert intent");
+                        break;
+                    }
+                }
+
+                match brain.state {
+                    AiState::Sleeping | AiState::Idle | AiState::Wandering if command == Some(AllyCommand::Follow) => {
+                        // Catch up with the player when there's nothing to fight
+                        let from_player = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
+                        if from_player > FOLLOW_DISTANCE {
+                            step_downhill(&mut map, pos, viewshed, Some(&flow_maps.player));
+                        }
+                    }
+                    AiState::Idle | AiState::Wandering if scavengers.get(entity).is_some() && command.is_none() &&
+                        flow_maps.items.get(map.xy_idx(pos.x, pos.y)).is_some_and(|d| *d <= SCAVENGE_RANGE) => {
+                        // Pick up anything lying here, or head for the nearest item
+                        let idx = map.xy_idx(pos.x, pos.y);
+                        if let Some(item) = map.tile_content[idx].iter().find(|e| items.get(**e).is_some()) {
+                            wants_pickup.insert(entity, WantsToPickupItem{ collected_by: entity, item: *item }).expect("Unable to insert want to pickup");
+                            break;
+                        }
+                        step_downhill(&mut map, pos, viewshed, Some(&flow_maps.items));
+                    }
+                    AiState::Sleeping | AiState::Idle => {}
+                    AiState::Wandering => {
+                        let x = pos.x + rng.range(-1, 2);
+                        let y = pos.y + rng.range(-1, 2);
+                        try_step(&mut map, pos, viewshed, x, y);
+                    }
+                    AiState::Hunting | AiState::Searching => {
+                        // Use spells and harmful items on a target in sight, keeping clear of their blast
+                        if let (Some(target), true) = (target, can_see_target) {
+                            let usable : Vec<Entity> = (&entities, &backpack, &ranged).join()
+                                .filter(|(e, pack, range)| pack.owner == entity && distance <= range.range as f32 &&
+                                    (inflicts_damage.get(*e).is_some() || inflicts_status.get(*e).is_some()) &&
+                                    charges.get(*e).is_none_or(|c| c.remaining > 0) &&
+                                    aoe.get(*e).is_none_or(|a| distance > (a.radius + 1) as f32))
+                                .map(|(e, _pack, _range)| e)
+                                .collect();
+                            if !usable.is_empty() && rng.roll_dice(1, CAST_CHANCE) == 1 {
+                                let item = *rng.random_slice_entry(&usable).unwrap();
+                                wants_to_use.insert(entity, WantsToUseItem{ item, target: Some(target.1) }).expect("Unable to insert intent");
+                                break;
+                            }
+                        }
+                        // Casters back off if their target gets too close
+                        if let (Some(caster), Some(target), true) = (casters.get(entity), target, can_see_target) {
+                            if distance < caster.preferred_distance as f32 &&
+                                (step_downhill(&mut map, pos, viewshed, flow_maps.flee.get(my_faction)) || step_away(&mut map, pos, viewshed, target.1)) {
+                                continue;
+                            }
+                        }
+                        if distance < 1.5 {
+                            wants_to_melee.insert(entity, WantsToMelee{ target: target.unwrap().0 }).expect("Unable to insert attack");
+                            break;
+                        }
+                        // Follow the faction's shared field towards its enemies while there's one in sight
+                        if can_see_target && !commanded && step_downhill(&mut map, pos, viewshed, flow_maps.approach.get(my_faction)) {
+                            continue;
+                        }
+                        // Otherwise head for wherever the faction last saw an enemy
+                        step_downhill(&mut map, pos, viewshed, flow_maps.search.get(my_faction));
+                    }
+                    AiState::Fleeing => {
+                        // Run for the safest ground in reach, or at least away from the threat,
+                        // and lash out if there's nowhere left to run
+                        if step_downhill(&mut map, pos, viewshed, flow_maps.flee.get(my_faction)) {
+                            continue;
+                        }
+                        let danger = match target {
+                            Some(t) => t.1,
+                            None => match brain.last_seen { Some(p) => p, None => break },
+                        };
+                        if !step_away(&mut map, pos, viewshed, danger) && distance < 1.5 {
+                            wants_to_melee.insert(entity, WantsToMelee{ target: target.unwrap().0 }).expect("Unable to insert attack");
+                            break;
+                        }
                     }
                 }
             }
+            update_position(&mut creatures, entity, pos);
         }
     }
-}
\ No newline at end of file
+}
+
+fn update_position(creatures: &mut [(Entity, Point, String)], entity: Entity, pos: &Position) {
+    if let Some(creature) = creatures.iter_mut().find(|c| c.0 == entity) {
+        creature.1 = Point::new(pos.x, pos.y);
+    }
+}
+
+fn open_tile(map: &Map, x: i32, y: i32) -> bool {
+    x > 0 && x < map.width - 1 && y > 0 && y < map.height - 1 && !map.blocked[map.xy_idx(x, y)]
+}
+
+/// Takes one step downhill on a shared flow map. Returns false if there is no map, or
+/// no lower ground to move to.
+fn step_downhill(map: &mut Map, pos: &mut Position, viewshed: &mut Viewshed, field: Option<&Vec<f32>>) -> bool {
+    match field.and_then(|field| FlowMaps::downhill(map, field, pos.x, pos.y)) {
+        Some((x, y)) => { try_step(map, pos, viewshed, x, y); true }
+        None => false,
+    }
+}
+
+/// Steps to whichever neighbouring tile is furthest from `danger`. Returns false if
+/// there was nowhere better to go.
+fn step_away(map: &mut Map, pos: &mut Position, viewshed: &mut Viewshed, danger: Point) -> bool {
+    let start = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), danger);
+    let mut best = (start, pos.x, pos.y);
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            let (x, y) = (pos.x + dx, pos.y + dy);
+            if !open_tile(map, x, y) { continue; }
+            let d = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(x, y), danger);
+            if d > best.0 { best = (d, x, y); }
+        }
+    }
+    if best.1 == pos.x && best.2 == pos.y { return false; }
+    try_step(map, pos, viewshed, best.1, best.2);
+    true
+}
+
+/// Moves a monster onto (x, y) if nothing is in the way.
+fn try_step(map: &mut Map, pos: &mut Position, viewshed: &mut Viewshed, x: i32, y: i32) {
+    if !open_tile(map, x, y) { return; }
+    let idx = map.xy_idx(pos.x, pos.y);
+    map.blocked[idx] = false;
+    pos.x = x;
+    pos.y = y;
+    let dest = map.xy_idx(x, y);
+    map.blocked[dest] = true;
+    viewshed.dirty = true;
+}
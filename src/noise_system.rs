@@ -0,0 +1,130 @@
+extern crate specs;
+use super::{
+    faction::{reaction, Reaction},
+    flow_map_system::flow_field_within,
+    gamelog::GameLog,
+    monster_ai_system::{DEBUG_AI, SEARCH_PATIENCE},
+    AiState, Brain, Faction, Hearing, MakesNoise, Map, Name, Position, Stealth,
+};
+use rltk::Point;
+use specs::prelude::*;
+
+/// How far various sounds carry, in tiles walked around walls.
+pub const FOOTSTEPS: i32 = 4;
+pub const SNEAKING: i32 = 1;
+pub const COMBAT: i32 = 8;
+pub const OPEN_CONTAINER: i32 = 4;
+pub const FORCE_LOCK: i32 = 10;
+/// Stealth can muffle a noise down to this, but never silence it completely.
+const MIN_VOLUME: i32 = 1;
+
+/// Records that `source` made a noise this turn. Only the loudest noise each turn counts.
+pub fn make_noise(noises: &mut WriteStorage<MakesNoise>, source: Entity, volume: i32) {
+    if noises.get(source).is_some_and(|n| n.volume >= volume) {
+        return;
+    }
+    noises
+        .insert(source, MakesNoise { volume })
+        .expect("Unable to insert noise");
+}
+
+pub struct NoiseSystem {}
+
+impl<'a> System<'a> for NoiseSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, MakesNoise>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Stealth>,
+        ReadStorage<'a, Hearing>,
+        ReadStorage<'a, Faction>,
+        WriteStorage<'a, Brain>,
+        ReadStorage<'a, Name>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            mut gamelog,
+            entities,
+            mut noises,
+            positions,
+            stealth,
+            hearing,
+            factions,
+            mut brains,
+            names,
+        ) = data;
+
+        // Nobody can hear a noise further off than its volume plus the sharpest ears about
+        let sharpest = hearing.join().map(|ears| ears.acuity).max().unwrap_or(0);
+
+        for (source, noise, source_pos) in (&entities, &noises, &positions).join() {
+            // However quiet you are, anyone right next to you can still hear you
+            let volume = i32::max(
+                MIN_VOLUME,
+                noise.volume - stealth.get(source).map_or(0, |s| s.skill),
+            );
+            let source_faction = factions.get(source).map_or("", |f| &f.name);
+            let sound = flow_field_within(
+                &map,
+                &[(map.xy_idx(source_pos.x, source_pos.y), 0.0)],
+                (volume + sharpest) as f32,
+            );
+
+            for (listener, ears, pos, brain) in
+                (&entities, &hearing, &positions, &mut brains).join()
+            {
+                if listener == source {
+                    continue;
+                }
+
+                // Sleepers only stir for noises at half the usual distance
+                let mut range = volume + ears.acuity;
+                if brain.state == AiState::Sleeping {
+                    range /= 2;
+                }
+                if sound[map.xy_idx(pos.x, pos.y)] > range as f32 {
+                    continue;
+                }
+
+                let my_faction = factions.get(listener).map_or("", |f| &f.name);
+                let new_state = match (reaction(my_faction, source_faction), brain.state) {
+                    (_, AiState::Hunting) | (_, AiState::Fleeing) | (Reaction::Ignore, _) => {
+                        continue
+                    }
+                    (Reaction::Attack, _) => AiState::Searching,
+                    (Reaction::Flee, _) => AiState::Fleeing,
+                };
+
+                if brain.state == AiState::Sleeping && map.visible_tiles[map.xy_idx(pos.x, pos.y)] {
+                    if let Some(name) = names.get(listener) {
+                        gamelog
+                            .entries
+                            .insert(0, format!("{} wakes up!", name.name));
+                    }
+                }
+                if DEBUG_AI && brain.state != new_state {
+                    let name = names.get(listener).map_or("Something", |n| &n.name);
+                    gamelog.entries.insert(
+                        0,
+                        format!(
+                            "[AI] {}: {:?} -> {:?} (heard a noise)",
+                            name, brain.state, new_state
+                        ),
+                    );
+                }
+
+                brain.state = new_state;
+                brain.target = Some(source);
+                brain.last_seen = Some(Point::new(source_pos.x, source_pos.y));
+                brain.patience = SEARCH_PATIENCE;
+            }
+        }
+
+        noises.clear();
+    }
+}
@@ -0,0 +1,117 @@
+extern crate specs;
+use super::{gui::Options, ParticleLifetime, Position, Renderable};
+use rltk::{Point, RGB};
+use specs::prelude::*;
+
+/// Drawn over everything else, including the player.
+const RENDER_LVL_PARTICLE: i32 = -1;
+
+struct ParticleRequest {
+    x: i32,
+    y: i32,
+    fg: RGB,
+    glyph: u8,
+    lifetime_ms: f32,
+}
+
+/// Particles asked for by systems this turn, spawned all at once by `ParticleSpawnSystem`.
+#[derive(Default)]
+pub struct ParticleBuilder {
+    requests: Vec<ParticleRequest>,
+}
+
+impl ParticleBuilder {
+    pub fn request(&mut self, x: i32, y: i32, fg: RGB, glyph: u8, lifetime_ms: f32) {
+        self.requests.push(ParticleRequest {
+            x,
+            y,
+            fg,
+            glyph,
+            lifetime_ms,
+        });
+    }
+
+    /// A streak of particles along the straight line from `start` to `end`, leaving out both ends.
+    pub fn trail(&mut self, start: Point, end: Point, fg: RGB, glyph: u8, lifetime_ms: f32) {
+        for point in rltk::line2d(rltk::LineAlg::Bresenham, start, end)
+            .iter()
+            .filter(|p| **p != start && **p != end)
+        {
+            self.request(point.x, point.y, fg, glyph, lifetime_ms);
+        }
+    }
+}
+
+pub struct ParticleSpawnSystem {}
+
+impl<'a> System<'a> for ParticleSpawnSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Renderable>,
+        WriteStorage<'a, ParticleLifetime>,
+        WriteExpect<'a, ParticleBuilder>,
+        ReadExpect<'a, Options>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut positions, mut renderables, mut particles, mut builder, options) = data;
+
+        // With effects turned off, requests are just thrown away
+        let requests: Vec<ParticleRequest> = builder.requests.drain(..).collect();
+        if !options.particles {
+            return;
+        }
+        for request in requests {
+            let particle = entities.create();
+            positions
+                .insert(
+                    particle,
+                    Position {
+                        x: request.x,
+                        y: request.y,
+                    },
+                )
+                .expect("Unable to insert position");
+            renderables
+                .insert(
+                    particle,
+                    Renderable {
+                        glyph: request.glyph,
+                        fg: request.fg,
+                        bg: RGB::named(rltk::BLACK),
+                        render_order: RENDER_LVL_PARTICLE,
+                    },
+                )
+                .expect("Unable to insert renderable");
+            particles
+                .insert(
+                    particle,
+                    ParticleLifetime {
+                        lifetime_ms: request.lifetime_ms,
+                    },
+                )
+                .expect("Unable to insert lifetime");
+        }
+    }
+}
+
+/// Ages every particle by the time the last frame took, and removes the ones that have expired.
+/// Runs every frame, whatever state the game is in, so effects fade out in real time.
+pub fn cull_dead_particles(ecs: &mut World, frame_time_ms: f32) {
+    let mut dead_particles: Vec<Entity> = Vec::new();
+    {
+        let mut particles = ecs.write_storage::<ParticleLifetime>();
+        let entities = ecs.entities();
+        for (entity, particle) in (&entities, &mut particles).join() {
+            particle.lifetime_ms -= frame_time_ms;
+            if particle.lifetime_ms < 0.0 {
+                dead_particles.push(entity);
+            }
+        }
+    }
+    for dead in dead_particles.iter() {
+        ecs.delete_entity(*dead).expect("Particle will not die");
+    }
+}
@@ -1,18 +1,40 @@
 use super::{
-    gamelog::GameLog, CombatStats, Item, Map, Player, Position, RunState, State, Viewshed,
-    WantsToMelee, WantsToPickupItem,
+    faction,
+    gamelog::GameLog,
+    gui::VendorMode,
+    noise_system::{make_noise, FOOTSTEPS, FORCE_LOCK, OPEN_CONTAINER, SNEAKING},
+    status_system::apply_status,
+    Ally, AllyCommand, CombatStats, Container, Faction, Hireable, InflictsStatus, Item, Locked,
+    MakesNoise, Map, Name, Player, Position, RunState, State, StatusEffect, StatusEffects,
+    StatusKind, Stealth, Trapped, Vendor, Viewshed, Wallet, WantsToMelee, WantsToPickupItem,
 };
-use rltk::{Point, Rltk, VirtualKeyCode};
+use rltk::{Point, RandomNumberGenerator, Rltk, VirtualKeyCode};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// How far away the player can point out a target to their allies.
+pub const COMMAND_RANGE: i32 = 8;
+
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> RunState {
+    let (delta_x, delta_y) = confused_direction(delta_x, delta_y, ecs);
+    if let Some(container) = find_at::<Container>(delta_x, delta_y, ecs) {
+        return open_container(container, ecs);
+    }
+    if let Some(ally) = find_at::<Ally>(delta_x, delta_y, ecs) {
+        return swap_places(ally, ecs);
+    }
+    if let Some(mercenary) = find_at::<Hireable>(delta_x, delta_y, ecs) {
+        return hire(mercenary, ecs);
+    }
+
     let mut positions = ecs.write_storage::<Position>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
     let players = ecs.write_storage::<Player>();
     let entities = ecs.entities();
     let combat_stats = ecs.read_storage::<CombatStats>();
+    let vendors = ecs.read_storage::<Vendor>();
+    let mut stealth = ecs.write_storage::<Stealth>();
+    let mut noises = ecs.write_storage::<MakesNoise>();
     let map = ecs.fetch::<Map>();
 
     for (entity, _player, pos, viewshed) in
@@ -23,11 +45,22 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             || pos.y + delta_y < 1
             || pos.y + delta_y > map.height - 1
         {
-            return;
+            return RunState::PlayerTurn;
         }
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
         for potential_target in map.tile_content[destination_idx].iter() {
+            if *potential_target == entity {
+                continue;
+            }
+            // Bumping into a merchant opens their shop instead of starting a fight
+            if vendors.get(*potential_target).is_some() {
+                return RunState::ShowVendor {
+                    vendor: *potential_target,
+                    mode: VendorMode::Buy,
+                };
+            }
+
             let target = combat_stats.get(*potential_target);
             if let Some(_target) = target {
                 wants_to_melee
@@ -38,20 +71,269 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("Add target failed");
-                return;
+                return RunState::PlayerTurn;
             }
         }
 
         if !map.blocked[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+            pos.x = (pos.x + delta_x).clamp(0, 79);
+            pos.y = (pos.y + delta_y).clamp(0, 49);
 
             viewshed.dirty = true;
+            let sneaking = sneak_step(&mut stealth, entity);
+            make_noise(
+                &mut noises,
+                entity,
+                if sneaking { SNEAKING } else { FOOTSTEPS },
+            );
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
             ppos.y = pos.y;
         }
     }
+
+    RunState::PlayerTurn
+}
+
+/// Finds something with a `T` on the tile the player is about to move into.
+fn find_at<T: Component>(delta_x: i32, delta_y: i32, ecs: &World) -> Option<Entity> {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let storage = ecs.read_storage::<T>();
+
+    let x = player_pos.x + delta_x;
+    let y = player_pos.y + delta_y;
+    if x < 1 || x > map.width - 1 || y < 1 || y > map.height - 1 {
+        return None;
+    }
+    let idx = map.xy_idx(x, y);
+    map.tile_content[idx]
+        .iter()
+        .find(|e| storage.get(**e).is_some())
+        .copied()
+}
+
+/// Bumping into an ally trades places with them rather than attacking.
+fn swap_places(ally: Entity, ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut positions = ecs.write_storage::<Position>();
+    let mut viewsheds = ecs.write_storage::<Viewshed>();
+    let mut noises = ecs.write_storage::<MakesNoise>();
+    let mut stealth = ecs.write_storage::<Stealth>();
+    let mut ppos = ecs.write_resource::<Point>();
+
+    let player_at = Point::new(ppos.x, ppos.y);
+    if let Some(pos) = positions.get_mut(ally) {
+        ppos.x = pos.x;
+        ppos.y = pos.y;
+        pos.x = player_at.x;
+        pos.y = player_at.y;
+    }
+    if let Some(pos) = positions.get_mut(player_entity) {
+        pos.x = ppos.x;
+        pos.y = ppos.y;
+    }
+    for entity in [player_entity, ally].iter() {
+        if let Some(viewshed) = viewsheds.get_mut(*entity) {
+            viewshed.dirty = true;
+        }
+    }
+    let sneaking = sneak_step(&mut stealth, player_entity);
+    make_noise(
+        &mut noises,
+        player_entity,
+        if sneaking { SNEAKING } else { FOOTSTEPS },
+    );
+
+    RunState::PlayerTurn
+}
+
+/// Notes that `entity` has taken a step, and returns true if it was a quiet one. A
+/// sneaking step leaves them creeping, so they sit out the next turn.
+fn sneak_step(stealth: &mut WriteStorage<Stealth>, entity: Entity) -> bool {
+    match stealth.get_mut(entity) {
+        Some(stealth) if stealth.sneaking => {
+            stealth.creeping = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Pays a mercenary to join the player, if the player can afford them.
+fn hire(mercenary: Entity, ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let mut wallets = ecs.write_storage::<Wallet>();
+    let mut hireable = ecs.write_storage::<Hireable>();
+    let mut factions = ecs.write_storage::<Faction>();
+    let mut allies = ecs.write_storage::<Ally>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+
+    let name = names.get(mercenary).map_or("Mercenary", |n| &n.name);
+    let fee = hireable.get(mercenary).map_or(0, |h| h.fee);
+    let wallet = match wallets.get_mut(player_entity) {
+        Some(wallet) if wallet.gold >= fee => wallet,
+        _ => {
+            gamelog
+                .entries
+                .insert(0, format!("The {} wants {} gold to join you.", name, fee));
+            return RunState::AwaitingInput;
+        }
+    };
+
+    wallet.gold -= fee;
+    hireable.remove(mercenary);
+    factions
+        .insert(
+            mercenary,
+            Faction {
+                name: faction::PLAYER.to_string(),
+            },
+        )
+        .expect("Unable to insert faction");
+    allies
+        .insert(
+            mercenary,
+            Ally {
+                command: AllyCommand::Follow,
+            },
+        )
+        .expect("Unable to insert ally");
+    gamelog
+        .entries
+        .insert(0, format!("You hire the {} for {} gold.", name, fee));
+
+    RunState::PlayerTurn
+}
+
+/// Gives every ally the same order.
+pub fn command_allies(ecs: &mut World, command: AllyCommand) {
+    let mut allies = ecs.write_storage::<Ally>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+
+    let mut count = 0;
+    for ally in (&mut allies).join() {
+        ally.command = command;
+        count += 1;
+    }
+    let message = match (count, command) {
+        (0, _) => "You have no one to command.",
+        (_, AllyCommand::Follow) => "You call your allies to follow you.",
+        (_, AllyCommand::Stay) => "You tell your allies to stay put.",
+        (_, AllyCommand::Attack(_)) => "You point out a target to your allies.",
+    };
+    gamelog.entries.insert(0, message.to_string());
+}
+
+/// Sets the allies on whatever is standing on the targeted tile. Returns false if there's
+/// nothing there to attack.
+pub fn command_allies_attack(ecs: &mut World, target: Point) -> bool {
+    let victim = {
+        let map = ecs.fetch::<Map>();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let allies = ecs.read_storage::<Ally>();
+        let player_entity = ecs.fetch::<Entity>();
+        map.tile_content[map.xy_idx(target.x, target.y)]
+            .iter()
+            .find(|e| {
+                combat_stats.get(**e).is_some()
+                    && allies.get(**e).is_none()
+                    && **e != *player_entity
+            })
+            .copied()
+    };
+    match victim {
+        Some(victim) => {
+            command_allies(ecs, AllyCommand::Attack(victim));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Tries to open a container: locks have to be forced (which takes a turn, and may fail),
+/// and traps go off in the player's face. Returns the container menu if it opens.
+fn open_container(container: Entity, ecs: &mut World) -> RunState {
+    let player_entity = *ecs.fetch::<Entity>();
+    let names = ecs.read_storage::<Name>();
+    let mut locks = ecs.write_storage::<Locked>();
+    let mut traps = ecs.write_storage::<Trapped>();
+    let trap_effects = ecs.read_storage::<InflictsStatus>();
+    let mut statuses = ecs.write_storage::<StatusEffects>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+    let mut noises = ecs.write_storage::<MakesNoise>();
+
+    let name = names
+        .get(container)
+        .map_or("container".to_string(), |n| n.name.to_lowercase());
+
+    if locks.get(container).is_some() {
+        let power = combat_stats.get(player_entity).map_or(0, |s| s.power);
+        make_noise(&mut noises, player_entity, FORCE_LOCK);
+        if rng.roll_dice(1, 20) + power < 15 {
+            gamelog.entries.insert(
+                0,
+                format!("The {} is locked. You fail to force it open.", name),
+            );
+            return RunState::PlayerTurn;
+        }
+        locks.remove(container);
+        gamelog
+            .entries
+            .insert(0, format!("You force the {} open.", name));
+    }
+
+    make_noise(&mut noises, player_entity, OPEN_CONTAINER);
+    if traps.get(container).is_some() {
+        traps.remove(container);
+        if let Some(trap) = trap_effects.get(container) {
+            apply_status(
+                &mut statuses,
+                player_entity,
+                StatusEffect {
+                    kind: trap.kind,
+                    turns: trap.turns,
+                    potency: trap.potency,
+                    source: None,
+                },
+            );
+            gamelog.entries.insert(
+                0,
+                format!("It's a trap! You are {}.", trap.kind.name().to_lowercase()),
+            );
+        }
+    }
+
+    RunState::ShowContainer { container }
+}
+
+/// A confused player stumbles off in a random direction instead of the one they asked for.
+fn confused_direction(delta_x: i32, delta_y: i32, ecs: &mut World) -> (i32, i32) {
+    let player_entity = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let confused = match statuses.get(*player_entity) {
+        None => false,
+        Some(status) => status.has(StatusKind::Confusion),
+    };
+    if !confused {
+        return (delta_x, delta_y);
+    }
+
+    let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+    gamelog
+        .entries
+        .insert(0, "You stumble around in confusion.".to_string());
+    // Standing still isn't a direction, and would have the player swing at themselves
+    loop {
+        let delta = (rng.range(-1, 2), rng.range(-1, 2));
+        if delta != (0, 0) {
+            return delta;
+        }
+    }
 }
 
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
@@ -61,29 +343,37 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
         Some(key) => match key {
             // Cardinals
             VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::H => {
-                try_move_player(-1, 0, &mut gs.ecs)
+                return try_move_player(-1, 0, &mut gs.ecs)
             }
 
             VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::L => {
-                try_move_player(1, 0, &mut gs.ecs)
+                return try_move_player(1, 0, &mut gs.ecs)
             }
 
             VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::K => {
-                try_move_player(0, -1, &mut gs.ecs)
+                return try_move_player(0, -1, &mut gs.ecs)
             }
 
             VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::J => {
-                try_move_player(0, 1, &mut gs.ecs)
+                return try_move_player(0, 1, &mut gs.ecs)
             }
 
             // Diagonals
-            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => try_move_player(1, -1, &mut gs.ecs),
+            VirtualKeyCode::Numpad9 | VirtualKeyCode::Y => {
+                return try_move_player(1, -1, &mut gs.ecs)
+            }
 
-            VirtualKeyCode::Numpad7 | VirtualKeyCode::U => try_move_player(-1, -1, &mut gs.ecs),
+            VirtualKeyCode::Numpad7 | VirtualKeyCode::U => {
+                return try_move_player(-1, -1, &mut gs.ecs)
+            }
 
-            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => try_move_player(1, 1, &mut gs.ecs),
+            VirtualKeyCode::Numpad3 | VirtualKeyCode::N => {
+                return try_move_player(1, 1, &mut gs.ecs)
+            }
 
-            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => try_move_player(-1, 1, &mut gs.ecs),
+            VirtualKeyCode::Numpad1 | VirtualKeyCode::B => {
+                return try_move_player(-1, 1, &mut gs.ecs)
+            }
 
             // Pickup
             VirtualKeyCode::G => get_item(&mut gs.ecs),
@@ -91,6 +381,16 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
             VirtualKeyCode::D => return RunState::ShowDropItem,
             // Inventory
             VirtualKeyCode::I => return RunState::ShowInventory,
+            // Throw
+            VirtualKeyCode::T => return RunState::ShowThrowItem,
+            // Command allies
+            VirtualKeyCode::C => return RunState::ShowAllyCommand,
+            VirtualKeyCode::Escape => return RunState::PauseMenu { selection: 0 },
+            // Sneak
+            VirtualKeyCode::S => {
+                toggle_sneaking(&mut gs.ecs);
+                return RunState::AwaitingInput;
+            }
 
             _ => return RunState::AwaitingInput,
         },
@@ -98,6 +398,83 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
     RunState::PlayerTurn
 }
 
+/// Switches the player between walking normally and sneaking about quietly.
+fn toggle_sneaking(ecs: &mut World) {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut stealth = ecs.write_storage::<Stealth>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+
+    if let Some(stealth) = stealth.get_mut(*player_entity) {
+        stealth.sneaking = !stealth.sneaking;
+        let message = if stealth.sneaking {
+            "You start creeping along quietly."
+        } else {
+            "You stop sneaking."
+        };
+        gamelog.entries.insert(0, message.to_string());
+    }
+}
+
+/// True if the player's status effects leave them able to act this turn, and they
+/// aren't still creeping after a sneaking step.
+pub fn player_can_act(ecs: &World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let stealth = ecs.read_storage::<Stealth>();
+    if stealth.get(*player_entity).is_some_and(|s| s.creeping) {
+        return false;
+    }
+    match statuses.get(*player_entity) {
+        None => true,
+        Some(status) => status.actions() > 0,
+    }
+}
+
+/// Logs why the player is sitting out a turn.
+pub fn player_lose_turn(ecs: &mut World) {
+    let player_entity = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let mut stealth = ecs.write_storage::<Stealth>();
+    let mut gamelog = ecs.write_resource::<GameLog>();
+    // Creeping along quietly takes its time, but isn't worth a message every step
+    if let Some(stealth) = stealth.get_mut(*player_entity) {
+        if stealth.creeping {
+            stealth.creeping = false;
+            return;
+        }
+    }
+    if let Some(status) = statuses.get(*player_entity) {
+        if status.has(StatusKind::Paralysis) {
+            gamelog
+                .entries
+                .insert(0, "You are paralysed and cannot move!".to_string());
+        } else if status.has(StatusKind::Slow) {
+            gamelog.entries.insert(
+                0,
+                "You are slowed, and the world moves on without you.".to_string(),
+            );
+        }
+    }
+}
+
+/// How many actions the player has taken so far this game turn.
+#[derive(Default)]
+pub struct PlayerActions {
+    pub taken: i32,
+}
+
+/// Counts an action the player has just taken, and returns true if their status effects
+/// give them another before the monsters get to move, as haste does.
+pub fn player_has_bonus_action(ecs: &World) -> bool {
+    let player_entity = ecs.fetch::<Entity>();
+    let statuses = ecs.read_storage::<StatusEffects>();
+    let mut actions = ecs.write_resource::<PlayerActions>();
+    actions.taken += 1;
+    statuses
+        .get(*player_entity)
+        .is_some_and(|status| status.actions() > actions.taken)
+}
+
 fn get_item(ecs: &mut World) {
     let player_pos = ecs.fetch::<Point>();
     let player_entity = ecs.fetch::<Entity>();
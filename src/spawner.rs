@@ -4,16 +4,54 @@ extern crate specs;
 use super::{
     map::MAPWIDTH, BlocksTile, CombatStats, Consumable, InflictsDamage, Item, Monster, Name,
     Player, Position, ProvidesHealing, Ranged, Rect, Renderable, Viewshed, AreaOfEffect,
-    Confusion
+    Charges, Gold, IdentifiesItems, InBackpack, InflictsStatus, StatusKind, Value, Vendor, Wallet,
+    Weight, Container, InContainer, Locked, Trapped, Fragile, MagicMapper, Teleports,
+    Buc, BucStatus, RemovesCurse, AiState, Brain, Faction, faction,
+    Caster, Spell, Hearing, Stealth, Unique, Map, StatusEffect, StatusEffects,
+    uniques::UniqueRegistry, status_system::apply_status, Ally, AllyCommand, Hireable, Charms,
+    Scavenger, LootTable, Corpse, LightSource, identification::{Identification, MagicKind}
 };
 use specs::prelude::*;
 
 const MAX_MONSTERS: i32 = 4;
 const MAX_ITEMS: i32 = 2;
+const VENDOR_STOCK: i32 = 5;
+const VENDOR_GOLD: i32 = 100;
+const MAX_CHEST_LOOT: i32 = 3;
+
+/// Every unique monster, the range of depths it can turn up at, and how to spawn it.
+#[allow(clippy::type_complexity)]
+const UNIQUES: &[(&str, i32, i32, fn(&mut World, i32, i32) -> Entity)] = &[
+    ("Grishnak the Goblin Warlord", 1, 2, grishnak),
+    ("Ulgra the Orc Witch", 1, 4, ulgra),
+];
+
+/// What a roll on a loot table can turn up: nothing at all, gold (rolled as N dice of
+/// the given size), or an item.
+#[derive(Clone, Copy)]
+enum Loot {
+    Nothing,
+    Gold(i32, i32),
+    Item(fn(&mut World, i32, i32) -> Entity),
+}
+
+/// Each loot table, with the relative weight of everything on it.
+#[allow(clippy::type_complexity)]
+const LOOT_TABLES: &[(&str, &[(i32, Loot)])] = &[
+    ("Orc", &[(6, Loot::Nothing), (3, Loot::Gold(2, 6)), (1, Loot::Item(health_potion)), (1, Loot::Item(random_item))]),
+    ("Goblin", &[(8, Loot::Nothing), (3, Loot::Gold(1, 6)), (1, Loot::Item(random_item))]),
+    ("Goblin Shaman", &[(3, Loot::Nothing), (2, Loot::Gold(2, 6)), (3, Loot::Item(random_item))]),
+    ("Mercenary", &[(1, Loot::Gold(3, 10))]),
+    ("Boss", &[(1, Loot::Gold(4, 10)), (2, Loot::Item(random_item)), (1, Loot::Item(health_potion))]),
+];
 
 const RENDER_LVL_PLAYER: i32 = 0;
 const RENDER_LVL_MOB: i32 = 1;
 const RENDER_LVL_ITEM: i32 = 2;
+const RENDER_LVL_DECOR: i32 = 3;
+
+/// How many turns a corpse lies around before it rots away.
+const CORPSE_DECAY: i32 = 200;
 
 /// Spawns the player and returns his/her entity object.
 pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
@@ -43,6 +81,19 @@ pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
             defense: 2,
             power: 5,
         })
+        .with(Wallet { gold: 0 })
+        .with(Faction {
+            name: faction::PLAYER.to_string(),
+        })
+        .with(Stealth {
+            skill: 1,
+            sneaking: false,
+            creeping: false,
+        })
+        .with(LightSource {
+            colour: RGB::from_f32(1.0, 0.9, 0.7),
+            range: 6,
+        })
         .build()
 }
 
@@ -51,22 +102,307 @@ pub fn random_monster(ecs: &mut World, x: i32, y: i32) {
     let roll: i32;
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 2);
+        roll = rng.roll_dice(1, 6);
     }
     match roll {
-        1 => orc(ecs, x, y),
-        _ => goblin(ecs, x, y),
+        1 | 2 => orc(ecs, x, y),
+        3 | 4 => goblin(ecs, x, y),
+        5 => goblin_shaman(ecs, x, y),
+        _ => rat(ecs, x, y),
     }
 }
 
 fn orc(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('o'), "Orc");
+    let orc = monster(ecs, x, y, rltk::to_cp437('o'), "Orc", faction::ORCS);
+    scavenger(ecs, orc);
+    loot_table(ecs, orc, "Orc", 1);
 }
 fn goblin(ecs: &mut World, x: i32, y: i32) {
-    monster(ecs, x, y, rltk::to_cp437('g'), "Goblin");
+    let goblin = monster(ecs, x, y, rltk::to_cp437('g'), "Goblin", faction::GOBLINS);
+    scavenger(ecs, goblin);
+    loot_table(ecs, goblin, "Goblin", 1);
+}
+/// Orcs and goblins pocket anything they find lying around, and aren't shy about using it.
+fn scavenger(ecs: &mut World, entity: Entity) {
+    ecs.write_storage::<Scavenger>()
+        .insert(entity, Scavenger {})
+        .expect("Unable to insert scavenger");
+}
+fn loot_table(ecs: &mut World, entity: Entity, table: &str, rolls: i32) {
+    ecs.write_storage::<LootTable>()
+        .insert(entity, LootTable { table: table.to_string(), rolls })
+        .expect("Unable to insert loot table");
+}
+
+/// Rolls once on the named loot table, leaving whatever turns up at (x, y).
+pub fn drop_loot(ecs: &mut World, table: &str, x: i32, y: i32) {
+    let entries = match LOOT_TABLES.iter().find(|t| t.0 == table) {
+        Some(t) => t.1,
+        None => return,
+    };
+    let loot = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        let total: i32 = entries.iter().map(|e| e.0).sum();
+        let mut roll = rng.roll_dice(1, total);
+        let mut loot = Loot::Nothing;
+        for (weight, entry) in entries.iter() {
+            if roll <= *weight {
+                loot = *entry;
+                break;
+            }
+            roll -= weight;
+        }
+        loot
+    };
+    match loot {
+        Loot::Nothing => {}
+        Loot::Gold(n, die) => {
+            let amount = ecs.write_resource::<RandomNumberGenerator>().roll_dice(n, die);
+            gold_pile(ecs, x, y, amount);
+        }
+        Loot::Item(spawn) => {
+            let item = spawn(ecs, x, y);
+            if ecs.read_storage::<Buc>().get(item).is_none() {
+                bless_or_curse(ecs, item);
+            }
+        }
+    }
+}
+/// Rats keep to themselves, and scurry off from anything that might eat them.
+fn rat(ecs: &mut World, x: i32, y: i32) {
+    let rat = monster(ecs, x, y, rltk::to_cp437('r'), "Rat", faction::WILDLIFE);
+    ecs.write_storage::<CombatStats>()
+        .insert(rat, CombatStats { max_hp: 6, hp: 6, defense: 0, power: 2 })
+        .expect("Unable to insert stats");
+    ecs.write_storage::<Hearing>()
+        .insert(rat, Hearing { acuity: 4 })
+        .expect("Unable to insert hearing");
+}
+
+/// The player's faithful dog, who starts the game at their side.
+pub fn dog(ecs: &mut World, x: i32, y: i32) {
+    let dog = monster(ecs, x, y, rltk::to_cp437('d'), "Dog", faction::PLAYER);
+    recolour(ecs, dog, rltk::to_cp437('d'), RGB::named(rltk::BURLYWOOD));
+    ecs.write_storage::<CombatStats>()
+        .insert(dog, CombatStats { max_hp: 12, hp: 12, defense: 0, power: 3 })
+        .expect("Unable to insert stats");
+    ecs.write_storage::<Hearing>()
+        .insert(dog, Hearing { acuity: 5 })
+        .expect("Unable to insert hearing");
+    ecs.write_storage::<Ally>()
+        .insert(dog, Ally { command: AllyCommand::Follow })
+        .expect("Unable to insert ally");
+    wake(ecs, dog);
+}
+
+/// A sellsword who minds their own business until someone pays them.
+fn mercenary(ecs: &mut World, x: i32, y: i32) {
+    let mercenary = monster(ecs, x, y, rltk::to_cp437('@'), "Mercenary", faction::MERCENARIES);
+    recolour(ecs, mercenary, rltk::to_cp437('@'), RGB::named(rltk::LIGHT_BLUE));
+    ecs.write_storage::<CombatStats>()
+        .insert(mercenary, CombatStats { max_hp: 20, hp: 20, defense: 1, power: 5 })
+        .expect("Unable to insert stats");
+    ecs.write_storage::<Hireable>()
+        .insert(mercenary, Hireable { fee: 40 })
+        .expect("Unable to insert fee");
+    loot_table(ecs, mercenary, "Mercenary", 1);
+    wake(ecs, mercenary);
+}
+
+/// Shamans hang back and hurl spells rather than wading into melee.
+fn goblin_shaman(ecs: &mut World, x: i32, y: i32) {
+    let shaman = monster(ecs, x, y, rltk::to_cp437('g'), "Goblin Shaman", faction::GOBLINS);
+    recolour(ecs, shaman, rltk::to_cp437('g'), RGB::named(rltk::MAGENTA));
+    scavenger(ecs, shaman);
+    ecs.write_storage::<LightSource>()
+        .insert(shaman, LightSource { colour: RGB::named(rltk::MAGENTA), range: 2 })
+        .expect("Unable to insert light");
+    loot_table(ecs, shaman, "Goblin Shaman", 1);
+    ecs.write_storage::<Caster>()
+        .insert(shaman, Caster { preferred_distance: 4 })
+        .expect("Unable to insert caster");
+
+    spell(ecs, shaman, "Magic Missile")
+        .with(InflictsDamage { damage: 5 })
+        .build();
+    spell(ecs, shaman, "Confusion")
+        .with(InflictsStatus { kind: StatusKind::Confusion, turns: 3, potency: 0 })
+        .build();
+    spell(ecs, shaman, "Fireball")
+        .with(InflictsDamage { damage: 8 })
+        .with(AreaOfEffect { radius: 1 })
+        .build();
+}
+
+/// Spawns every unique monster that belongs at this depth and hasn't been met yet this run,
+/// each in a room of its own away from the player's starting room and the shop.
+pub fn spawn_uniques(ecs: &mut World, map: &Map) {
+    let mut rooms: Vec<usize> = (1..map.rooms.len().saturating_sub(1)).collect();
+    for (name, min_depth, max_depth, spawn) in UNIQUES.iter() {
+        if map.depth < *min_depth || map.depth > *max_depth
+            || !ecs.fetch::<UniqueRegistry>().available(name)
+        {
+            continue;
+        }
+        if rooms.is_empty() {
+            return;
+        }
+        let room_idx = {
+            let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+            let pick = rng.random_slice_index(&rooms).unwrap();
+            rooms.remove(pick)
+        };
+        let (x, y) = match free_tile(ecs, &map.rooms[room_idx]) {
+            Some(tile) => tile,
+            None => continue,
+        };
+        let boss = spawn(ecs, x, y);
+        ecs.write_storage::<Unique>()
+            .insert(boss, Unique {})
+            .expect("Unable to insert unique");
+        ecs.write_resource::<UniqueRegistry>().spawned.insert(name.to_string());
+    }
+}
+
+/// A tile in the room that nothing has been spawned on yet, preferring the middle.
+fn free_tile(ecs: &World, room: &Rect) -> Option<(i32, i32)> {
+    let positions = ecs.read_storage::<Position>();
+    let taken = |x: i32, y: i32| positions.join().any(|pos| pos.x == x && pos.y == y);
+
+    let (cx, cy) = room.center();
+    if !taken(cx, cy) {
+        return Some((cx, cy));
+    }
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            if !taken(x, y) {
+                return Some((x, y));
+            }
+        }
+    }
+    None
+}
+
+/// Bosses never doze off on the job, and always leave a hoard behind.
+fn boss<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: u8, name: S, faction: &str, stats: CombatStats) -> Entity {
+    let boss = monster(ecs, x, y, glyph, name, faction);
+    ecs.write_storage::<CombatStats>()
+        .insert(boss, stats)
+        .expect("Unable to insert stats");
+    wake(ecs, boss);
+    loot_table(ecs, boss, "Boss", 3);
+    boss
+}
+
+/// Makes sure a monster starts out awake.
+fn wake(ecs: &mut World, entity: Entity) {
+    ecs.write_storage::<Brain>()
+        .insert(entity, Brain { state: AiState::Idle, target: None, last_seen: None, patience: 0 })
+        .expect("Unable to insert brain");
+}
+
+/// The goblins' warlord moves at twice the speed of anything else in the dungeon.
+fn grishnak(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let grishnak = boss(ecs, x, y, rltk::to_cp437('G'), "Grishnak the Goblin Warlord",
+        faction::GOBLINS, CombatStats { max_hp: 40, hp: 40, defense: 2, power: 7 });
+    recolour(ecs, grishnak, rltk::to_cp437('G'), RGB::named(rltk::ORANGE));
+    ecs.write_storage::<Hearing>()
+        .insert(grishnak, Hearing { acuity: 5 })
+        .expect("Unable to insert hearing");
+    let mut statuses = ecs.write_storage::<StatusEffects>();
+    apply_status(&mut statuses, grishnak, StatusEffect { kind: StatusKind::Haste, turns: i32::MAX, potency: 0, source: None });
+    drop(statuses);
+
+    let wand = magic_missile_wand(ecs, x, y);
+    give(ecs, grishnak, wand);
+    let potion = speed_potion(ecs, x, y);
+    give(ecs, grishnak, potion);
+    grishnak
+}
+
+/// A witch who poisons from afar, and keeps her distance doing it.
+fn ulgra(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let ulgra = boss(ecs, x, y, rltk::to_cp437('O'), "Ulgra the Orc Witch",
+        faction::ORCS, CombatStats { max_hp: 30, hp: 30, defense: 1, power: 5 });
+    recolour(ecs, ulgra, rltk::to_cp437('O'), RGB::named(rltk::PURPLE));
+    ecs.write_storage::<Caster>()
+        .insert(ulgra, Caster { preferred_distance: 5 })
+        .expect("Unable to insert caster");
+
+    spell(ecs, ulgra, "Venom Bolt")
+        .with(InflictsStatus { kind: StatusKind::Poison, turns: 5, potency: 2 })
+        .build();
+    spell(ecs, ulgra, "Fireball")
+        .with(InflictsDamage { damage: 10 })
+        .with(AreaOfEffect { radius: 1 })
+        .build();
+
+    let potion = regeneration_potion(ecs, x, y);
+    give(ecs, ulgra, potion);
+    let scroll = confusion_scroll(ecs, x, y);
+    give(ecs, ulgra, scroll);
+    ulgra
+}
+
+/// Moves a freshly spawned item into someone's backpack.
+fn give(ecs: &mut World, owner: Entity, item: Entity) {
+    ecs.write_storage::<Position>().remove(item);
+    ecs.write_storage::<InBackpack>()
+        .insert(item, InBackpack { owner })
+        .expect("Unable to give item");
+}
+
+/// Swaps out a monster's glyph and colour.
+fn recolour(ecs: &mut World, entity: Entity, glyph: u8, fg: RGB) {
+    ecs.write_storage::<Renderable>()
+        .insert(entity, Renderable {
+            glyph,
+            fg,
+            bg: RGB::named(rltk::BLACK),
+            render_order: RENDER_LVL_MOB,
+        })
+        .expect("Unable to insert renderable");
+}
+
+/// Starts building a scroll or potion, disguised until the player identifies its kind.
+/// Everything of one kind looks the same on the map, so the colour gives nothing away.
+fn magic_item<'a>(ecs: &'a mut World, x: i32, y: i32, name: &str, kind: MagicKind) -> EntityBuilder<'a> {
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        ecs.write_resource::<Identification>().disguise(&mut rng, name, kind);
+    }
+    let (glyph, fg, weight) = match kind {
+        MagicKind::Scroll => (rltk::to_cp437(')'), RGB::named(rltk::CYAN), 1),
+        MagicKind::Potion => (rltk::to_cp437('¡'), RGB::named(rltk::MAGENTA), 2),
+    };
+    let builder = ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable { glyph, fg, bg: RGB::named(rltk::BLACK), render_order: RENDER_LVL_ITEM })
+        .with(Name { name: name.to_string() })
+        .with(Item {})
+        .with(Weight { weight })
+        .with(Consumable {});
+    match kind {
+        MagicKind::Potion => builder.with(Fragile {}),
+        MagicKind::Scroll => builder,
+    }
 }
 
-fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: u8, name: S) {
+/// Starts building a spell known by the given caster.
+fn spell<'a>(ecs: &'a mut World, caster: Entity, name: &str) -> EntityBuilder<'a> {
+    ecs.create_entity()
+        .with(Name { name: name.to_string() })
+        .with(Spell {})
+        .with(Ranged { range: 6 })
+        .with(InBackpack { owner: caster })
+}
+
+fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: u8, name: S, faction: &str) -> Entity {
+    // A third of the dungeon's inhabitants are asleep when the player arrives
+    let state = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        if rng.roll_dice(1, 3) == 1 { AiState::Sleeping } else { AiState::Idle }
+    };
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
@@ -81,6 +417,12 @@ fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: u8, name: S) {
             dirty: true,
         })
         .with(Monster {})
+        .with(Brain {
+            state,
+            target: None,
+            last_seen: None,
+            patience: 0,
+        })
         .with(Name {
             name: name.to_string(),
         })
@@ -91,92 +433,324 @@ fn monster<S: ToString>(ecs: &mut World, x: i32, y: i32, glyph: u8, name: S) {
             defense: 1,
             power: 4,
         })
-        .build();
+        .with(Faction {
+            name: faction.to_string(),
+        })
+        .with(Hearing { acuity: 2 })
+        .build()
+}
+
+fn health_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Health Potion", MagicKind::Potion)
+        .with(Value { gold: 20 })
+        .with(ProvidesHealing { heal_amount: 8 })
+        .build()
+}
+
+fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Magic Missile Scroll", MagicKind::Scroll)
+        .with(Value { gold: 15 })
+        .with(Ranged { range: 6 })
+        .with(InflictsDamage { damage: 8 })
+        .build()
+}
+
+fn fireball_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Fireball Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 40 })
+        .with(Ranged{ range: 6 })
+        .with(InflictsDamage{ damage: 20 })
+        .with(AreaOfEffect{ radius: 3 })
+        .build()
+}
+
+fn confusion_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Confusion Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 25 })
+        .with(Ranged{ range: 6 })
+        .with(InflictsStatus{ kind: StatusKind::Confusion, turns: 4, potency: 0 })
+        .build()
+}
+
+fn poison_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Poison Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 25 })
+        .with(Ranged{ range: 6 })
+        .with(InflictsStatus{ kind: StatusKind::Poison, turns: 6, potency: 2 })
+        .build()
+}
+
+fn identify_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Identify Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 20 })
+        .with(IdentifiesItems{})
+        .build()
+}
+
+fn remove_curse_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Remove Curse Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 40 })
+        .with(RemovesCurse{})
+        .build()
+}
+
+fn charm_monster_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Charm Monster Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 60 })
+        .with(Ranged{ range: 6 })
+        .with(Charms{})
+        .build()
 }
 
-fn health_potion(ecs: &mut World, x: i32, y: i32) {
+fn regeneration_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Regeneration Potion", MagicKind::Potion)
+        .with(Value { gold: 30 })
+        .with(InflictsStatus { kind: StatusKind::Regeneration, turns: 10, potency: 1 })
+        .build()
+}
+
+fn speed_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Speed Potion", MagicKind::Potion)
+        .with(Value { gold: 35 })
+        .with(InflictsStatus { kind: StatusKind::Haste, turns: 10, potency: 0 })
+        .build()
+}
+
+fn magic_mapping_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Magic Mapping Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 40 })
+        .with(MagicMapper{})
+        .build()
+}
+
+fn teleport_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Teleport Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 30 })
+        .with(Teleports{})
+        .build()
+}
+
+fn blink_scroll(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Blink Scroll", MagicKind::Scroll)
+        .with(Value{ gold: 45 })
+        .with(Ranged{ range: 8 })
+        .with(Teleports{})
+        .build()
+}
+
+fn telepathy_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Telepathy Potion", MagicKind::Potion)
+        .with(Value { gold: 35 })
+        .with(InflictsStatus { kind: StatusKind::DetectMonsters, turns: 20, potency: 0 })
+        .build()
+}
+
+fn object_detection_potion(ecs: &mut World, x: i32, y: i32) -> Entity {
+    magic_item(ecs, x, y, "Object Detection Potion", MagicKind::Potion)
+        .with(Value { gold: 25 })
+        .with(InflictsStatus { kind: StatusKind::DetectItems, turns: 20, potency: 0 })
+        .build()
+}
+
+fn magic_missile_wand(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let charges: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        charges = rng.roll_dice(1, 3) + 2;
+    }
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph: rltk::to_cp437('¡'),
-            fg: RGB::named(rltk::MAGENTA),
+            glyph: rltk::to_cp437('/'),
+            fg: RGB::named(rltk::CYAN),
             bg: RGB::named(rltk::BLACK),
             render_order: RENDER_LVL_ITEM,
         })
         .with(Name {
-            name: "Health Potion".to_string(),
+            name: "Magic Missile Wand".to_string(),
         })
         .with(Item {})
-        .with(Consumable {})
-        .with(ProvidesHealing { heal_amount: 8 })
-        .build();
+        .with(Weight { weight: 3 })
+        .with(Value { gold: 50 })
+        .with(Charges { remaining: charges })
+        .with(Ranged { range: 6 })
+        .with(InflictsDamage { damage: 6 })
+        .build()
 }
 
-fn magic_missile_scroll(ecs: &mut World, x: i32, y: i32) {
+fn random_item(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let roll: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        roll = rng.roll_dice(1, 16);
+    }
+    let item = match roll {
+        1 => health_potion(ecs, x, y),
+        2 => fireball_scroll(ecs, x, y),
+        3 => confusion_scroll(ecs, x, y),
+        4 => poison_scroll(ecs, x, y),
+        5 => regeneration_potion(ecs, x, y),
+        6 => speed_potion(ecs, x, y),
+        7 => identify_scroll(ecs, x, y),
+        8 => magic_missile_wand(ecs, x, y),
+        9 => magic_mapping_scroll(ecs, x, y),
+        10 => teleport_scroll(ecs, x, y),
+        11 => blink_scroll(ecs, x, y),
+        12 => telepathy_potion(ecs, x, y),
+        13 => object_detection_potion(ecs, x, y),
+        14 => remove_curse_scroll(ecs, x, y),
+        15 => charm_monster_scroll(ecs, x, y),
+        _ => magic_missile_scroll(ecs, x, y),
+    };
+
+    bless_or_curse(ecs, item);
+    item
+}
+
+/// Roughly one item in ten is cursed, and another one in ten blessed.
+fn bless_or_curse(ecs: &mut World, item: Entity) {
+    let status = {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        match rng.roll_dice(1, 10) {
+            1 => BucStatus::Cursed,
+            10 => BucStatus::Blessed,
+            _ => BucStatus::Uncursed,
+        }
+    };
+    ecs.write_storage::<Buc>()
+        .insert(item, Buc{ status, known: false })
+        .expect("Unable to insert blessing");
+}
+
+fn gold(ecs: &mut World, x: i32, y: i32) {
+    let amount: i32;
+    {
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+        amount = rng.roll_dice(2, 10);
+    }
+    gold_pile(ecs, x, y, amount);
+}
+
+fn gold_pile(ecs: &mut World, x: i32, y: i32, amount: i32) {
     ecs.create_entity()
         .with(Position { x, y })
         .with(Renderable {
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::CYAN),
+            glyph: rltk::to_cp437('$'),
+            fg: RGB::named(rltk::GOLD),
             bg: RGB::named(rltk::BLACK),
             render_order: RENDER_LVL_ITEM,
         })
         .with(Name {
-            name: "Magic Missile Scroll".to_string(),
+            name: "Pile of Gold".to_string(),
         })
-        .with(Item {})
-        .with(Consumable {})
-        .with(Ranged { range: 6 })
-        .with(InflictsDamage { damage: 8 })
+        .with(Gold { amount })
         .build();
 }
 
-fn fireball_scroll(ecs: &mut World, x: i32, y: i32) {
+/// Leaves the remains of `name` at (x, y), in the colour it had in life.
+pub fn corpse(ecs: &mut World, x: i32, y: i32, name: &str, fg: RGB) {
     ecs.create_entity()
-        .with(Position{ x, y })
-        .with(Renderable{
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::ORANGE),
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('%'),
+            fg,
             bg: RGB::named(rltk::BLACK),
-            render_order: RENDER_LVL_ITEM
+            render_order: RENDER_LVL_DECOR,
         })
-        .with(Name{ name : "Fireball Scroll".to_string() })
-        .with(Item{})
-        .with(Consumable{})
-        .with(Ranged{ range: 6 })
-        .with(InflictsDamage{ damage: 20 })
-        .with(AreaOfEffect{ radius: 3 })
+        .with(Name {
+            name: format!("{} corpse", name),
+        })
+        .with(Corpse { decay: CORPSE_DECAY })
         .build();
 }
 
-fn confusion_scroll(ecs: &mut World, x: i32, y: i32) {
+fn torch(ecs: &mut World, x: i32, y: i32) {
     ecs.create_entity()
-        .with(Position{ x, y })
-        .with(Renderable{
-            glyph: rltk::to_cp437(')'),
-            fg: RGB::named(rltk::PINK),
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('☼'),
+            fg: RGB::named(rltk::YELLOW),
             bg: RGB::named(rltk::BLACK),
-            render_order: 2
+            render_order: RENDER_LVL_DECOR,
+        })
+        .with(Name {
+            name: "Torch".to_string(),
+        })
+        .with(LightSource {
+            colour: RGB::from_f32(1.0, 0.6, 0.2),
+            range: 8,
         })
-        .with(Name{ name : "Confusion Scroll".to_string() })
-        .with(Item{})
-        .with(Consumable{})
-        .with(Ranged{ range: 6 })
-        .with(Confusion{ turns: 4 })
         .build();
 }
 
-fn random_item(ecs: &mut World, x: i32, y: i32) {
-    let roll: i32;
+/// Spawns a merchant carrying a few random items to sell.
+pub fn vendor(ecs: &mut World, x: i32, y: i32) {
+    let merchant = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('@'),
+            fg: RGB::named(rltk::GREEN),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RENDER_LVL_MOB,
+        })
+        .with(Name {
+            name: "Merchant".to_string(),
+        })
+        .with(Vendor {})
+        .with(Wallet { gold: VENDOR_GOLD })
+        .with(BlocksTile {})
+        .build();
+
+    for _i in 0..VENDOR_STOCK {
+        let item = random_item(ecs, x, y);
+        give(ecs, merchant, item);
+    }
+}
+
+/// Spawns a chest holding a few random items. Some are locked, and some are trapped with
+/// either a poison needle or a cloud of paralysing gas.
+fn chest(ecs: &mut World, x: i32, y: i32) {
+    let (loot, locked, trap);
     {
         let mut rng = ecs.write_resource::<RandomNumberGenerator>();
-        roll = rng.roll_dice(1, 4);
+        loot = rng.roll_dice(1, MAX_CHEST_LOOT);
+        locked = rng.roll_dice(1, 3) == 1;
+        trap = match rng.roll_dice(1, 8) {
+            1 => Some(InflictsStatus { kind: StatusKind::Poison, turns: 5, potency: 2 }),
+            2 => Some(InflictsStatus { kind: StatusKind::Paralysis, turns: 3, potency: 0 }),
+            _ => None,
+        };
     }
-    match roll {
-        1 => health_potion(ecs, x, y),
-        2 => fireball_scroll(ecs, x, y),
-        3 => confusion_scroll(ecs, x, y),
-        _ => magic_missile_scroll(ecs, x, y),
+
+    let mut builder = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('='),
+            fg: RGB::named(rltk::BROWN1),
+            bg: RGB::named(rltk::BLACK),
+            render_order: RENDER_LVL_ITEM,
+        })
+        .with(Name {
+            name: "Chest".to_string(),
+        })
+        .with(Container {})
+        .with(BlocksTile {});
+    if locked {
+        builder = builder.with(Locked {});
+    }
+    if let Some(trap) = trap {
+        builder = builder.with(Trapped {}).with(trap);
+    }
+    let chest = builder.build();
+
+    for _i in 0..loot {
+        let item = random_item(ecs, x, y);
+        ecs.write_storage::<Position>().remove(item);
+        ecs.write_storage::<InContainer>()
+            .insert(item, InContainer { container: chest })
+            .expect("Unable to fill chest");
     }
 }
 
@@ -184,6 +758,10 @@ fn random_item(ecs: &mut World, x: i32, y: i32) {
 pub fn spawn_room(ecs: &mut World, room: &Rect) {
     let mut monster_spawn_points: Vec<usize> = Vec::new();
     let mut item_spawn_points: Vec<usize> = Vec::new();
+    let mut gold_spawn_point: Option<(i32, i32)> = None;
+    let mut chest_spawn_point: Option<(i32, i32)> = None;
+    let mut mercenary_spawn_point: Option<(i32, i32)> = None;
+    let mut torch_spawn_point: Option<(i32, i32)> = None;
 
     // Scope to keep the borrow checker happy
     {
@@ -191,6 +769,21 @@ pub fn spawn_room(ecs: &mut World, room: &Rect) {
         let num_monsters = rng.roll_dice(1, MAX_MONSTERS + 2) - 3;
         let num_items = rng.roll_dice(1, MAX_ITEMS + 2) - 3;
 
+        // One room in six has a chest, tucked away in the room's corner. Nothing else
+        // goes on that tile, or it would be stuck inside the chest.
+        let mut reserved: Vec<usize> = Vec::new();
+        if rng.roll_dice(1, 6) == 1 {
+            chest_spawn_point = Some((room.x1 + 1, room.y1 + 1));
+            reserved.push(((room.y1 + 1) as usize * MAPWIDTH) + (room.x1 + 1) as usize);
+        }
+
+        // Now and then a sellsword is waiting in the opposite corner for someone to hire
+        // them, with the tile to themselves
+        if rng.roll_dice(1, 12) == 1 {
+            mercenary_spawn_point = Some((room.x2 - 1, room.y2 - 1));
+            reserved.push(((room.y2 - 1) as usize * MAPWIDTH) + (room.x2 - 1) as usize);
+        }
+
         // Create Monster Spawn points
         for _i in 0..num_monsters {
             let mut added = false;
@@ -198,7 +791,7 @@ pub fn spawn_room(ecs: &mut World, room: &Rect) {
                 let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
                 let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
                 let idx = (y * MAPWIDTH) + x;
-                if !monster_spawn_points.contains(&idx) {
+                if !monster_spawn_points.contains(&idx) && !reserved.contains(&idx) {
                     monster_spawn_points.push(idx);
                     added = true;
                 }
@@ -211,12 +804,29 @@ pub fn spawn_room(ecs: &mut World, room: &Rect) {
                 let x = (room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1))) as usize;
                 let y = (room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1))) as usize;
                 let idx = (y * MAPWIDTH) + x;
-                if !item_spawn_points.contains(&idx) {
+                if !item_spawn_points.contains(&idx) && !reserved.contains(&idx) {
                     item_spawn_points.push(idx);
                     added = true;
                 }
             }
         }
+
+        // One room in three has some gold lying around
+        if rng.roll_dice(1, 3) == 1 {
+            loop {
+                let x = room.x1 + rng.roll_dice(1, i32::abs(room.x2 - room.x1));
+                let y = room.y1 + rng.roll_dice(1, i32::abs(room.y2 - room.y1));
+                if !reserved.contains(&((y as usize * MAPWIDTH) + x as usize)) {
+                    gold_spawn_point = Some((x, y));
+                    break;
+                }
+            }
+        }
+
+        // Every third room or so has a torch burning in another corner
+        if rng.roll_dice(1, 3) == 1 {
+            torch_spawn_point = Some((room.x2 - 1, room.y1 + 1));
+        }
     }
 
     // Spawn Monsters
@@ -231,4 +841,20 @@ pub fn spawn_room(ecs: &mut World, room: &Rect) {
         let y = *idx / MAPWIDTH;
         random_item(ecs, x as i32, y as i32);
     }
+    // And the gold
+    if let Some((x, y)) = gold_spawn_point {
+        gold(ecs, x, y);
+    }
+    // And the chest
+    if let Some((x, y)) = chest_spawn_point {
+        chest(ecs, x, y);
+    }
+    // And the mercenary
+    if let Some((x, y)) = mercenary_spawn_point {
+        mercenary(ecs, x, y);
+    }
+    // And the torch
+    if let Some((x, y)) = torch_spawn_point {
+        torch(ecs, x, y);
+    }
 }
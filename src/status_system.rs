@@ -0,0 +1,164 @@
+extern crate specs;
+use super::{
+    gamelog::GameLog, CombatStats, LastHitBy, Map, Name, Player, Position, RunState, StatusEffect,
+    StatusEffects, StatusKind, Viewshed,
+};
+use specs::prelude::*;
+
+pub struct StatusSystem {}
+
+impl<'a> System<'a> for StatusSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, RunState>,
+        ReadExpect<'a, Map>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, Viewshed>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Position>,
+        WriteStorage<'a, LastHitBy>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            runstate,
+            map,
+            mut log,
+            entities,
+            mut statuses,
+            mut combat_stats,
+            mut viewsheds,
+            players,
+            names,
+            positions,
+            mut last_hit,
+        ) = data;
+
+        // Statuses tick once a game turn for everyone, however many actions they get in it
+        if *runstate != RunState::MonsterTurn {
+            return;
+        }
+
+        for (entity, status) in (&entities, &mut statuses).join() {
+            let is_player = players.get(entity).is_some();
+            if status.effects.is_empty() {
+                continue;
+            }
+
+            // Only tell the player about things they can see
+            let mut noticed = is_player;
+            if let Some(pos) = positions.get(entity) {
+                noticed |= map.visible_tiles[map.xy_idx(pos.x, pos.y)];
+            }
+            let name = match names.get(entity) {
+                Some(name) => name.name.to_string(),
+                None => "Something".to_string(),
+            };
+
+            for effect in status.effects.iter_mut() {
+                if let Some(stats) = combat_stats.get_mut(entity) {
+                    match effect.kind {
+                        StatusKind::Poison => {
+                            stats.hp -= effect.potency;
+                            // Whoever hurt them last, it's the poisoner who's hurting them now
+                            match effect.source {
+                                Some(source) => {
+                                    let poisoner =
+                                        names.get(source).map_or("poison".to_string(), |n| {
+                                            format!("{}'s poison", n.name)
+                                        });
+                                    last_hit
+                                        .insert(
+                                            entity,
+                                            LastHitBy {
+                                                source,
+                                                name: poisoner,
+                                            },
+                                        )
+                                        .expect("Unable to insert last hit");
+                                }
+                                None => {
+                                    last_hit.remove(entity);
+                                }
+                            }
+                            if noticed {
+                                log.entries.insert(
+                                    0,
+                                    format!("{} suffers {} hp from poison.", name, effect.potency),
+                                );
+                            }
+                        }
+                        StatusKind::Regeneration => {
+                            stats.hp = i32::min(stats.max_hp, stats.hp + effect.potency);
+                        }
+                        _ => {}
+                    }
+                }
+                effect.turns -= 1;
+            }
+
+            for effect in status.effects.iter().filter(|e| e.turns < 1) {
+                if effect.kind == StatusKind::Blindness {
+                    if let Some(viewshed) = viewsheds.get_mut(entity) {
+                        viewshed.dirty = true;
+                    }
+                }
+                if noticed {
+                    log.entries.insert(
+                        0,
+                        format!(
+                            "{} is no longer {}.",
+                            name,
+                            effect.kind.name().to_lowercase()
+                        ),
+                    );
+                }
+            }
+            status.effects.retain(|e| e.turns > 0);
+        }
+    }
+}
+
+/// Adds a status to an entity, following the stacking rules: haste and slow
+/// cancel each other out, poison intensifies, and anything else refreshes its duration.
+pub fn apply_status(
+    statuses: &mut WriteStorage<StatusEffects>,
+    target: Entity,
+    effect: StatusEffect,
+) {
+    if statuses.get(target).is_none() {
+        statuses
+            .insert(target, StatusEffects::default())
+            .expect("Unable to insert status");
+    }
+    let status = statuses.get_mut(target).unwrap();
+
+    let opposite = match effect.kind {
+        StatusKind::Haste => Some(StatusKind::Slow),
+        StatusKind::Slow => Some(StatusKind::Haste),
+        _ => None,
+    };
+    if let Some(opposite) = opposite {
+        if status.has(opposite) {
+            status.effects.retain(|e| e.kind != opposite);
+            return;
+        }
+    }
+
+    match status.effects.iter_mut().find(|e| e.kind == effect.kind) {
+        Some(existing) => {
+            if effect.kind.intensifies() {
+                existing.potency += effect.potency;
+            }
+            if effect.source.is_some() {
+                existing.source = effect.source;
+            }
+            existing.turns = i32::max(existing.turns, effect.turns);
+        }
+        None => status.effects.push(effect),
+    }
+}
@@ -0,0 +1,15 @@
+use std::collections::HashSet;
+
+/// Keeps track of the one-of-a-kind monsters met this run, so that none of them
+/// turns up twice, or comes back after being killed.
+#[derive(Default)]
+pub struct UniqueRegistry {
+    pub spawned: HashSet<String>,
+}
+
+impl UniqueRegistry {
+    /// True if the named unique hasn't appeared yet this run.
+    pub fn available(&self, name: &str) -> bool {
+        !self.spawned.contains(name)
+    }
+}
@@ -1,5 +1,5 @@
 extern crate specs;
-use super::{Map, Player, Position, Viewshed};
+use super::{Map, Player, Position, StatusEffects, StatusKind, Viewshed};
 use specs::prelude::*;
 extern crate rltk;
 use rltk::{field_of_view, Point};
@@ -13,17 +13,25 @@ impl<'a> System<'a> for VisibilitySystem {
         WriteStorage<'a, Viewshed>,
         WriteStorage<'a, Position>,
         ReadStorage<'a, Player>,
+        ReadStorage<'a, StatusEffects>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut map, entities, mut viewshed, pos, player) = data;
+        let (mut map, entities, mut viewshed, pos, player, statuses) = data;
 
         for (ent, viewshed, pos) in (&entities, &mut viewshed, &pos).join() {
             if viewshed.dirty {
                 viewshed.dirty = false;
                 viewshed.visible_tiles.clear();
-                viewshed.visible_tiles =
-                    field_of_view(Point::new(pos.x, pos.y), viewshed.range, &*map);
+
+                // Blindness shrinks sight down to the adjacent tiles
+                let mut range = viewshed.range;
+                if let Some(status) = statuses.get(ent) {
+                    if status.has(StatusKind::Blindness) {
+                        range = 1;
+                    }
+                }
+                viewshed.visible_tiles = field_of_view(Point::new(pos.x, pos.y), range, &*map);
                 viewshed
                     .visible_tiles
                     .retain(|p| p.x > 0 && p.x < map.width - 1 && p.y > 0 && p.y < map.height - 1);
@@ -31,6 +39,12 @@ impl<'a> System<'a> for VisibilitySystem {
                 // If this is the player, reveal what they can see
                 let _p: Option<&Player> = player.get(ent);
                 if let Some(_p) = _p {
+                    // Only lit tiles can be seen, save for whatever is close enough to touch
+                    let here = Point::new(pos.x, pos.y);
+                    viewshed.visible_tiles.retain(|p| {
+                        map.is_lit(map.xy_idx(p.x, p.y))
+                            || rltk::DistanceAlg::Pythagoras.distance2d(here, *p) < 1.5
+                    });
                     for t in map.visible_tiles.iter_mut() {
                         *t = false
                     }